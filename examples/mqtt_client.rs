@@ -1,4 +1,5 @@
-use bambu::MqttClient;
+use bambu::{MqttClient, MqttEvent};
+use futures_util::StreamExt;
 
 pub(crate) struct Config {
     pub(crate) printer_ip: String,
@@ -39,11 +40,17 @@ async fn main() {
         &config.access_code,
         &config.serial_number,
     );
-    let task = client.start().await.expect("Start failed");
+    let mut events = client.subscribe();
+    let _task = client.start().await.expect("Start failed");
 
     client.push_all().await.expect("foo");
 
     println!("Client started");
 
-    task.await.expect("Task failed");
+    while let Some(event) = events.next().await {
+        match event {
+            MqttEvent::Status(status) => println!("Status: {status:?}"),
+            other => println!("Event: {other:?}"),
+        }
+    }
 }
@@ -8,15 +8,12 @@ use smol_str::SmolStr;
 use tokio::net::TcpStream;
 use tokio_rustls::{
     client::TlsStream,
-    rustls::{
-        pki_types::{IpAddr, ServerName},
-        ClientConfig,
-    },
+    rustls::pki_types::{IpAddr, ServerName},
     TlsConnector,
 };
 use tokio_util::codec::Framed;
 
-use crate::tls::NoVerifier;
+use crate::tls::{self, CertificatePolicy};
 
 const DEFAULT_CAMERA_USERNAME: &str = "bblp";
 
@@ -25,6 +22,7 @@ pub struct CameraClient {
     hostname: String,
     access_code: String,
     port: u16,
+    certificate_policy: CertificatePolicy,
 }
 
 impl CameraClient {
@@ -34,9 +32,16 @@ impl CameraClient {
             hostname: hostname.to_string(),
             access_code: access_code.to_string(),
             port,
+            certificate_policy: CertificatePolicy::default(),
         }
     }
 
+    /// Set how the printer certificate is verified. Defaults to [`CertificatePolicy::Insecure`],
+    /// which accepts any certificate.
+    pub fn set_certificate_policy(&mut self, policy: CertificatePolicy) {
+        self.certificate_policy = policy;
+    }
+
     /// Connect via TCP + TLS, send the auth packet, and then return a `Framed`
     /// that uses `JpegCodec` to decode JPEG frames from the socket.
     pub async fn connect_and_stream_codec(
@@ -49,12 +54,7 @@ impl CameraClient {
         // CryptoProvider::install();
 
         // 2) Create a rustls ClientConfig
-        let config = ClientConfig::builder()
-            .dangerous()
-            .with_custom_certificate_verifier(Arc::new(NoVerifier))
-            .with_no_client_auth();
-
-        let config = Arc::new(config);
+        let config = Arc::new(tls::client_config(&self.certificate_policy));
         let connector = TlsConnector::from(config);
 
         // 3) Wrap in tokio-rustls for async TLS
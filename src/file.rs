@@ -3,7 +3,9 @@ pub(crate) mod ftp;
 
 use std::io;
 
-use crate::tls::NoVerifier;
+use tokio::io::AsyncRead;
+
+use crate::tls::CertificatePolicy;
 use ftp::{metadata::FileMetadata, FtpClient};
 
 /// An async FTPS file client, similar to the Python version using curl.
@@ -11,6 +13,7 @@ use ftp::{metadata::FileMetadata, FtpClient};
 pub struct FileClient {
     hostname: String,
     access_code: String,
+    certificate_policy: CertificatePolicy,
 }
 
 impl FileClient {
@@ -24,9 +27,16 @@ impl FileClient {
         Self {
             hostname: hostname.into(),
             access_code: access_code.into(),
+            certificate_policy: CertificatePolicy::default(),
         }
     }
 
+    /// Set how the printer certificate is verified. Defaults to [`CertificatePolicy::Insecure`],
+    /// which accepts any certificate.
+    pub fn set_certificate_policy(&mut self, policy: CertificatePolicy) {
+        self.certificate_policy = policy;
+    }
+
     /// List files in the given `directory`, filtering by `extension`.
     /// This is roughly equivalent to running:
     /// `curl --ftp-pasv --insecure ftps://HOSTNAME/DIRECTORY --user bblp:ACCESS_CODE`.
@@ -37,6 +47,7 @@ impl FileClient {
             self.hostname.clone(),
             "bblp".to_string(),
             self.access_code.clone(),
+            &self.certificate_policy,
         )
         .await
         .unwrap();
@@ -45,4 +56,45 @@ impl FileClient {
         client.quit().await?;
         Ok(files)
     }
+
+    /// Upload `contents` to `path` and check that the printer stored all of it by listing the
+    /// parent directory. Roughly equivalent to running:
+    /// `curl --ftp-pasv --insecure -T FILE ftps://HOSTNAME/PATH --user bblp:ACCESS_CODE`.
+    pub async fn upload_file(
+        &self,
+        path: &str,
+        mut contents: impl AsyncRead + Unpin,
+    ) -> io::Result<FileMetadata> {
+        let (directory, filename) = match path.rsplit_once('/') {
+            Some(("", filename)) => ("/", filename),
+            Some((directory, filename)) => (directory, filename),
+            None => ("/", path),
+        };
+
+        let mut client = FtpClient::connect(
+            self.hostname.clone(),
+            "bblp".to_string(),
+            self.access_code.clone(),
+            &self.certificate_policy,
+        )
+        .await?;
+        client.authenticate().await?;
+        let size = client.store(path, &mut contents).await?;
+        // NOTE: This also closes the connection.
+        let files = client.list_files(directory).await?;
+
+        let file = files
+            .into_iter()
+            .find(|file| file.filename == filename)
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "Uploaded file is not listed")
+            })?;
+        if file.size != size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Uploaded {size} bytes but the printer stored {}", file.size),
+            ));
+        }
+        Ok(file)
+    }
 }
@@ -8,6 +8,7 @@ use std::io;
 use std::net::{SocketAddr, ToSocketAddrs};
 use std::str::FromStr;
 use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tokio_rustls::client::TlsStream;
 use tokio_rustls::rustls::pki_types;
@@ -15,7 +16,7 @@ use tokio_rustls::rustls::ClientConfig;
 use tokio_rustls::TlsConnector;
 use tokio_util::codec::{Framed, LinesCodec};
 
-use super::NoVerifier;
+use crate::tls::{self, CertificatePolicy};
 
 const FTPS_PORT: u16 = 990;
 
@@ -23,22 +24,31 @@ pub struct FtpClient {
     hostname: String,
     username: String,
     password: String,
+    config: Arc<ClientConfig>,
     framed: Framed<TlsStream<TcpStream>, FtpCodec>,
 }
 
 impl FtpClient {
-    pub async fn connect(hostname: String, username: String, password: String) -> io::Result<Self> {
+    pub async fn connect(
+        hostname: String,
+        username: String,
+        password: String,
+        policy: &CertificatePolicy,
+    ) -> io::Result<Self> {
         let port = FTPS_PORT;
         // TCP connection
 
         let socket_addr = (hostname.as_str(), port).to_socket_addrs()?.next().unwrap();
 
-        let framed = connect_insecure(socket_addr, FtpCodec).await?;
+        // The data connections are verified against the same policy.
+        let config = Arc::new(tls::client_config(policy));
+        let framed = connect_tls(socket_addr, config.clone(), FtpCodec).await?;
 
         Ok(Self {
             hostname,
             username,
             password,
+            config,
             framed,
         })
     }
@@ -193,23 +203,73 @@ impl FtpClient {
         Ok(())
     }
 
-    /// Connects to the FTP server and lists files in the given directory.
-    pub async fn list_files(&mut self, directory: &str) -> io::Result<Vec<FileMetadata>> {
-        let pwd = self.pwd().await?;
-        println!("Current directory: {}", pwd);
+    /// Enter passive mode and return the address of the data connection.
+    async fn passive_mode(&mut self) -> io::Result<SocketAddr> {
+        match self.send_command(FtpRequest::EnterPassiveMode).await? {
+            FtpResponse::EnteringPassiveMode(socket_addr) => Ok(socket_addr),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid passive mode response",
+            )),
+        }
+    }
 
-        // Enter passive mode
-        let pasv_response = self.send_command(FtpRequest::EnterPassiveMode).await?;
+    /// Uploads `contents` to `path` in binary mode and returns the number of bytes sent.
+    pub async fn store(
+        &mut self,
+        path: &str,
+        contents: &mut (impl AsyncRead + Unpin),
+    ) -> io::Result<u64> {
+        match self.send_command(FtpRequest::Type("I".to_string())).await? {
+            FtpResponse::CommandOkay(_) => {}
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Invalid TYPE response",
+                ));
+            }
+        }
 
-        let socket_addr = match pasv_response {
-            FtpResponse::EnteringPassiveMode(socket_addr) => socket_addr,
+        let socket_addr = self.passive_mode().await?;
+
+        match self
+            .send_command(FtpRequest::Store(path.to_string()))
+            .await?
+        {
+            FtpResponse::FileStatusOkay(_) => {}
+            FtpResponse::FileUnavailable(message) => {
+                return Err(io::Error::new(io::ErrorKind::PermissionDenied, message));
+            }
             _ => {
                 return Err(io::Error::new(
                     io::ErrorKind::InvalidData,
-                    "Invalid passive mode response",
+                    "Invalid STOR response",
                 ));
             }
-        };
+        }
+
+        // Send the file over the data connection. Shutting it down sends the TLS close_notify,
+        // which tells the server that the upload is complete.
+        let mut data_stream = connect_tls_stream(socket_addr, self.config.clone()).await?;
+        let size = tokio::io::copy(contents, &mut data_stream).await?;
+        data_stream.shutdown().await?;
+
+        match self.framed.next().await.transpose()? {
+            Some(FtpResponse::ClosingDataConnection(_)) => Ok(size),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Upload was not acknowledged",
+            )),
+        }
+    }
+
+    /// Connects to the FTP server and lists files in the given directory.
+    pub async fn list_files(&mut self, directory: &str) -> io::Result<Vec<FileMetadata>> {
+        let pwd = self.pwd().await?;
+        println!("Current directory: {}", pwd);
+
+        // Enter passive mode
+        let socket_addr = self.passive_mode().await?;
 
         let response = self
             .send_command(FtpRequest::List(directory.to_string()))
@@ -230,7 +290,8 @@ impl FtpClient {
 
         // Connect to the data stream
         let lines = {
-            let mut data_framed = connect_insecure(socket_addr, LinesCodec::new()).await?;
+            let mut data_framed =
+                connect_tls(socket_addr, self.config.clone(), LinesCodec::new()).await?;
             println!("Connected to {:?}", socket_addr);
 
             let mut lines = Vec::new();
@@ -257,18 +318,24 @@ impl FtpClient {
     }
 }
 
-async fn connect_insecure<C>(
+async fn connect_tls<C>(
     address: SocketAddr,
+    config: Arc<ClientConfig>,
     codec: C,
 ) -> Result<Framed<TlsStream<TcpStream>, C>, io::Error> {
+    let tls_stream = connect_tls_stream(address, config).await?;
+    let framed = Framed::new(tls_stream, codec);
+    Ok(framed)
+}
+
+async fn connect_tls_stream(
+    address: SocketAddr,
+    config: Arc<ClientConfig>,
+) -> Result<TlsStream<TcpStream>, io::Error> {
     let tcp_stream = TcpStream::connect(address).await?;
     // tcp_stream.
 
-    let config: ClientConfig = ClientConfig::builder()
-        .dangerous()
-        .with_custom_certificate_verifier(Arc::new(NoVerifier))
-        .with_no_client_auth();
-    let connector = TlsConnector::from(Arc::new(config));
+    let connector = TlsConnector::from(config);
 
     let tls_stream = connector
         .connect(
@@ -276,6 +343,5 @@ async fn connect_insecure<C>(
             tcp_stream,
         )
         .await?;
-    let framed = Framed::new(tls_stream, codec);
-    Ok(framed)
+    Ok(tls_stream)
 }
@@ -23,6 +23,8 @@ pub enum FtpRequest {
     ProtectionBufferSize(u32), // Protection Buffer Size
     ProtectionLevel(String),   // Protection Level
     Pwd,                       // Print Working Directory
+    Type(String),              // Representation type, "I" for binary
+    Store(String),             // Upload a file
 }
 
 impl FtpRequest {
@@ -37,6 +39,8 @@ impl FtpRequest {
             FtpRequest::ProtectionBufferSize(size) => format_smolstr!("PBSZ {}", size),
             FtpRequest::ProtectionLevel(level) => format_smolstr!("PROT {}", level),
             FtpRequest::Pwd => SmolStr::new_static("PWD"),
+            FtpRequest::Type(representation) => format_smolstr!("TYPE {}", representation),
+            FtpRequest::Store(path) => format_smolstr!("STOR {}", path),
         }
     }
 }
@@ -229,4 +233,16 @@ mod tests {
             _ => panic!("Expected EnteringPassiveMode"),
         }
     }
+
+    #[test]
+    fn test_store_request() {
+        let mut buffer = BytesMut::new();
+        FtpCodec
+            .encode(
+                FtpRequest::Store("/3D Benchy.gcode.3mf".into()),
+                &mut buffer,
+            )
+            .unwrap();
+        assert_eq!(&buffer[..], b"STOR /3D Benchy.gcode.3mf\r\n");
+    }
 }
@@ -0,0 +1,264 @@
+//! Uploading a file and printing it in one go.
+use std::{io, time::Duration};
+
+use smol_str::SmolStr;
+use thiserror::Error;
+use tokio::io::AsyncRead;
+
+use crate::{
+    mqtt::message::print::{GcodeState, Print},
+    mqtt::{command::print::ProjectFile, MqttError},
+    FileClient, MqttClient,
+};
+
+#[derive(Debug, Error)]
+pub enum PrintJobError {
+    #[error("Upload failed: {0}")]
+    Upload(#[from] io::Error),
+    #[error(transparent)]
+    Mqtt(#[from] MqttError),
+    #[error("Printer rejected the print job: {0}")]
+    Rejected(SmolStr),
+    #[error("Print failed to start ({state}, error {print_error:08X})")]
+    Failed { state: GcodeState, print_error: u64 },
+    #[error("Timed out waiting for the print to start")]
+    Timeout,
+}
+
+/// Upload `contents` to the SD card at the location given by the url of `project`, start
+/// printing it and wait until the printer reports `RUNNING`.
+///
+/// `mqtt` must be started. A full report is requested first to check that the printer is not
+/// busy, waiting for at most the default timeout of `mqtt`, and [`MqttError::Busy`] is returned
+/// if it is. The printer can spend several minutes heating and leveling the bed before it starts
+/// printing, so `timeout` should be generous. `None` waits forever.
+///
+/// Returns the printer status once the print is running.
+pub async fn upload_and_print(
+    files: &FileClient,
+    mqtt: &mut MqttClient,
+    contents: impl AsyncRead + Unpin,
+    project: ProjectFile,
+    timeout: Option<Duration>,
+) -> Result<Print, PrintJobError> {
+    project.validate()?;
+    let path = format!("/{}", project.path().unwrap_or_default());
+
+    let mut state = mqtt.state();
+    let snapshot = mqtt.refresh_state().await?;
+    let initial = snapshot.status();
+    if let Some(
+        busy
+        @ (GcodeState::Prepare | GcodeState::Slicing | GcodeState::Running | GcodeState::Pause),
+    ) = &initial.gcode_state
+    {
+        return Err(MqttError::Busy(busy.clone()).into());
+    }
+
+    files.upload_file(&path, contents).await?;
+
+    let response = mqtt.start_print(project).await?;
+    if let Some(reason) = response.rejection() {
+        return Err(PrintJobError::Rejected(reason.into()));
+    }
+
+    let mut watcher = JobWatcher::new(initial);
+    let wait = async {
+        loop {
+            if let Some(result) = watcher.update(state.borrow_and_update().status()) {
+                return result;
+            }
+            // NOTE: The sender lives as long as `mqtt`, so this only fails once it is dropped.
+            if state.changed().await.is_err() {
+                return Err(PrintJobError::Mqtt(MqttError::Disconnected));
+            }
+        }
+    };
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, wait)
+            .await
+            .map_err(|_| PrintJobError::Timeout)?,
+        None => wait.await,
+    }
+}
+
+/// Follows `gcode_state` after sending a print job.
+///
+/// Reports still describing the previous job are ignored until the printer picks up the new one,
+/// so that e.g. a stale `IDLE` or `FAILED` is not mistaken for a failure of the new job.
+struct JobWatcher {
+    task_id: Option<SmolStr>,
+    subtask_id: Option<SmolStr>,
+    started: bool,
+}
+
+impl JobWatcher {
+    /// `initial` is the status before the job was sent, in which the printer was not busy.
+    fn new(initial: &Print) -> Self {
+        Self {
+            task_id: initial.task_id.clone(),
+            subtask_id: initial.subtask_id.clone(),
+            started: false,
+        }
+    }
+
+    /// Returns the outcome of the job once `status` settles it.
+    fn update(&mut self, status: &Print) -> Option<Result<Print, PrintJobError>> {
+        let state = status.gcode_state.as_ref()?;
+        if !self.started {
+            // The printer was idle before, so any busy state or a new task belongs to the job.
+            self.started = matches!(
+                state,
+                GcodeState::Prepare | GcodeState::Slicing | GcodeState::Running | GcodeState::Pause
+            ) || status.task_id != self.task_id
+                || status.subtask_id != self.subtask_id;
+            if !self.started {
+                return None;
+            }
+        }
+
+        let print_error = status.print_error.unwrap_or_default();
+        match state {
+            GcodeState::Running => Some(Ok(status.clone())),
+            GcodeState::Failed | GcodeState::Idle | GcodeState::Finish => {
+                Some(Err(PrintJobError::Failed {
+                    state: state.clone(),
+                    print_error,
+                }))
+            }
+            _ if print_error != 0 => Some(Err(PrintJobError::Failed {
+                state: state.clone(),
+                print_error,
+            })),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::mqtt::testing::FakeBroker;
+
+    fn status(state: GcodeState, print_error: u64) -> Print {
+        Print {
+            gcode_state: Some(state),
+            print_error: Some(print_error),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn waits_for_running() {
+        let mut watcher = JobWatcher::new(&status(GcodeState::Finish, 0));
+        assert!(watcher.update(&status(GcodeState::Finish, 0)).is_none());
+        assert!(watcher.update(&status(GcodeState::Prepare, 0)).is_none());
+        assert!(matches!(
+            watcher.update(&status(GcodeState::Running, 0)),
+            Some(Ok(_))
+        ));
+    }
+
+    #[test]
+    fn ignores_previous_failure() {
+        let mut watcher = JobWatcher::new(&status(GcodeState::Failed, 0x0300_4000));
+        assert!(watcher
+            .update(&status(GcodeState::Failed, 0x0300_4000))
+            .is_none());
+        assert!(watcher.update(&status(GcodeState::Prepare, 0)).is_none());
+        assert!(matches!(
+            watcher.update(&status(GcodeState::Failed, 0x0700_8011)),
+            Some(Err(PrintJobError::Failed {
+                state: GcodeState::Failed,
+                print_error: 0x0700_8011
+            }))
+        ));
+    }
+
+    #[test]
+    fn ignores_stale_idle_without_previous_state() {
+        let mut watcher = JobWatcher::new(&Print::default());
+        assert!(watcher.update(&status(GcodeState::Idle, 0)).is_none());
+        assert!(watcher.update(&status(GcodeState::Finish, 0)).is_none());
+        assert!(watcher.update(&status(GcodeState::Slicing, 0)).is_none());
+        assert!(matches!(
+            watcher.update(&status(GcodeState::Idle, 0)),
+            Some(Err(PrintJobError::Failed {
+                state: GcodeState::Idle,
+                ..
+            }))
+        ));
+
+        // A new task is the job, even if the printer skipped reporting PREPARE.
+        let mut watcher = JobWatcher::new(&Print::default());
+        let failed = Print {
+            task_id: Some("1234".into()),
+            ..status(GcodeState::Failed, 0x0500_4014)
+        };
+        assert!(matches!(
+            watcher.update(&failed),
+            Some(Err(PrintJobError::Failed { .. }))
+        ));
+    }
+
+    #[test]
+    fn fails_on_print_error() {
+        let mut watcher = JobWatcher::new(&Print::default());
+        assert!(matches!(
+            watcher.update(&status(GcodeState::Pause, 0x0C00_8015)),
+            Some(Err(PrintJobError::Failed {
+                state: GcodeState::Pause,
+                ..
+            }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn refuses_busy_printer() {
+        let broker = FakeBroker::bind().await;
+        let mut mqtt = broker.client();
+        let (started, mut connection) = tokio::join!(mqtt.start(), broker.accept());
+        started.unwrap();
+
+        let files = FileClient::new("127.0.0.1", "12345678");
+        let project = ProjectFile::new("file:///sdcard/benchy.gcode.3mf");
+        let (result, ()) = tokio::join!(
+            upload_and_print(&files, &mut mqtt, &b""[..], project, None),
+            async {
+                connection.next_request().await;
+                connection
+                    .publish(json!({"print": {
+                        "command": "push_status",
+                        "sequence_id": "0",
+                        "msg": 0,
+                        "gcode_state": "RUNNING"
+                    }}))
+                    .await;
+            }
+        );
+        assert!(
+            matches!(
+                result,
+                Err(PrintJobError::Mqtt(MqttError::Busy(GcodeState::Running)))
+            ),
+            "{result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn validates_before_upload() {
+        let files = FileClient::new("127.0.0.1", "12345678");
+        let mut mqtt = MqttClient::new("127.0.0.1", "12345678", "01S00C000000000");
+        let project = ProjectFile::new("file:///sdcard/benchy.stl");
+        let result = upload_and_print(&files, &mut mqtt, &b""[..], project, None).await;
+        assert!(
+            matches!(
+                result,
+                Err(PrintJobError::Mqtt(MqttError::InvalidArgument(_)))
+            ),
+            "{result:?}"
+        );
+    }
+}
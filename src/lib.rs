@@ -1,8 +1,20 @@
 mod camera;
 mod file;
+mod job;
 mod mqtt;
-pub(crate) mod tls;
+mod tls;
 
 pub use camera::{codec::CameraPacket, codec::JpegCodec as CameraCodec, CameraClient};
 pub use file::FileClient;
-pub use mqtt::{command, message, MqttClient};
+pub use job::{upload_and_print, PrintJobError};
+pub use mqtt::{
+    builder::{MqttClientBuilder, MqttTransport},
+    command,
+    event::{ConnectionState, HmsEvent, MqttEvent},
+    message,
+    model::{CameraStream, Capabilities, PrinterModel},
+    reconnect::ReconnectPolicy,
+    state::PrinterState,
+    MqttClient, MqttError,
+};
+pub use tls::{CertificateAuthority, CertificatePin, CertificatePolicy};
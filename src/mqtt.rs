@@ -1,88 +1,65 @@
+pub mod builder;
 pub mod command;
+pub mod event;
 pub mod message;
+pub mod model;
+pub mod reconnect;
+pub mod state;
+#[cfg(test)]
+pub(crate) mod testing;
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
 
 use anyhow::Result;
+use builder::{MqttClientBuilder, MqttTransport};
+use chrono::Utc;
 use command::{
+    calibration::{CalibrationOptions, FlowRateCalibration, PressureAdvance},
+    camera::{CameraCommand, CameraPayload, CameraResolution, Toggle},
+    gcode::{self, Axis, Fan, GcodeBuilder, Heater, Positioning},
     info::{InfoCommand, InfoPayload},
-    print::{PrintCommand, PrintPayload},
+    print::{AmsControl, PrintCommand, PrintPayload, ProjectFile, SpeedLevel, TrayFilament},
     pushing::{PushingCommand, PushingPayload},
-    system::{LedCtrl, LedMode, LedNode, SystemCommand, SystemPayload},
+    system::{
+        AccessoryType, LedCtrl, LedMode, LedNode, NozzleType, SystemCommand, SystemPayload,
+        NOZZLE_DIAMETERS,
+    },
+    xcam::{AiMonitoringConfig, Sensitivity, XcamCommand, XcamControl, XcamModule, XcamPayload},
     Command,
 };
-use rumqttc::tokio_rustls::rustls::{
-    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
-    pki_types::{CertificateDer, ServerName, UnixTime},
-    DigitallySignedStruct, Error, SignatureScheme,
-};
+use event::{ConnectionState, HmsEvent, MqttEvent};
+use futures_core::Stream;
+use model::{CameraStream, Capabilities, PrinterModel};
+use reconnect::ReconnectPolicy;
 use rumqttc::{
-    tokio_rustls::rustls::ClientConfig, AsyncClient, ClientError, Event, MqttOptions, Packet, QoS,
-    TlsConfiguration, Transport,
+    AsyncClient, ClientError, Event, MqttOptions, Packet, QoS, TlsConfiguration, Transport,
 };
 
 use smol_str::{format_smolstr, SmolStr};
 use thiserror::Error;
 use tokio::{
-    sync::{oneshot, Mutex},
+    sync::{broadcast, oneshot, watch, Mutex},
     task::JoinHandle,
     time::Duration,
 };
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream, WatchStream},
+    StreamExt,
+};
 
-use message::{info::Info, system::System, Message};
-
-/// NOTE: I had to duplicate this due to crate version mismatch. Once rumqttc is updated, this can be removed.
-#[derive(Debug)]
-pub(crate) struct RumqttcNoVerifier;
-
-impl ServerCertVerifier for RumqttcNoVerifier {
-    fn verify_server_cert(
-        &self,
-        _end_entity: &CertificateDer,
-        _intermediates: &[CertificateDer],
-        _server_name: &ServerName,
-        _ocsp_response: &[u8],
-        _now: UnixTime,
-    ) -> Result<ServerCertVerified, Error> {
-        Ok(ServerCertVerified::assertion())
-    }
-
-    fn verify_tls12_signature(
-        &self,
-        _message: &[u8],
-        _cert: &CertificateDer,
-        _dss: &DigitallySignedStruct,
-    ) -> Result<HandshakeSignatureValid, Error> {
-        Ok(HandshakeSignatureValid::assertion())
-    }
-
-    fn verify_tls13_signature(
-        &self,
-        _message: &[u8],
-        _cert: &CertificateDer,
-        _dss: &DigitallySignedStruct,
-    ) -> Result<HandshakeSignatureValid, Error> {
-        Ok(HandshakeSignatureValid::assertion())
-    }
-
-    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
-        vec![
-            SignatureScheme::RSA_PKCS1_SHA1,
-            SignatureScheme::ECDSA_SHA1_Legacy,
-            SignatureScheme::RSA_PKCS1_SHA256,
-            SignatureScheme::ECDSA_NISTP256_SHA256,
-            SignatureScheme::RSA_PKCS1_SHA384,
-            SignatureScheme::ECDSA_NISTP384_SHA384,
-            SignatureScheme::RSA_PKCS1_SHA512,
-            SignatureScheme::ECDSA_NISTP521_SHA512,
-            SignatureScheme::RSA_PSS_SHA256,
-            SignatureScheme::RSA_PSS_SHA384,
-            SignatureScheme::RSA_PSS_SHA512,
-            SignatureScheme::ED25519,
-            SignatureScheme::ED448,
-        ]
-    }
-}
+use crate::tls::{self, CertificatePolicy};
+use message::{
+    ams::TrayLocation,
+    hms::Hms,
+    info::Info,
+    print::{GcodeState, Ipcam, Print},
+    system::{AccessCode, System, SystemResponse},
+    Message, TryFromMessageError,
+};
+use state::PrinterState;
 
 #[derive(Debug, Error)]
 pub enum MqttError {
@@ -90,40 +67,139 @@ pub enum MqttError {
     ClientError(#[from] ClientError),
     #[error("Failed to serialize command: {0}")]
     SerdeError(#[from] serde_json::Error),
+    #[error("Connection to the printer was lost")]
+    Disconnected,
+    #[error("Timed out waiting for a response")]
+    Timeout,
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(SmolStr),
+    #[error("Printer is busy ({0})")]
+    Busy(GcodeState),
+    #[error("The {model} does not support {feature}")]
+    Unsupported {
+        model: PrinterModel,
+        feature: SmolStr,
+    },
+    #[error("Unexpected response: {0}")]
+    UnexpectedResponse(#[from] TryFromMessageError),
 }
 
 const DEFAULT_MQTT_ID: &str = "bblp_client";
 const DEFAULT_MQTT_PORT: u16 = 8883;
 const DEFAULT_MQTT_USERNAME: &str = "bblp";
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(60);
+/// Shortest keep-alive interval accepted by `rumqttc`.
+const MIN_KEEP_ALIVE: Duration = Duration::from_secs(1);
+const DEFAULT_CLIENT_CAPACITY: usize = 10;
+const DEFAULT_EVENT_CAPACITY: usize = 64;
+const DEFAULT_COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest extrusion or retraction accepted by [`MqttClient::extrude`], in mm.
+const MAX_EXTRUDE_LENGTH: f64 = 100.0;
+/// Highest feedrate accepted for manual moves, in mm/min.
+const MAX_FEEDRATE: u32 = 30_000;
 
 /// Main watch client.
 pub struct MqttClient {
     hostname: String,
+    port: u16,
+    client_id: String,
+    username: String,
+    keep_alive: Duration,
+    transport: MqttTransport,
+    certificate_policy: CertificatePolicy,
+    /// Capacity of the channel between `client` and its event loop.
+    capacity: usize,
     access_code: String,
     serial: String,
+    /// Model identified by `get_version`, used when the serial number is not recognized.
+    detected_model: PrinterModel,
     /// We'll store a reference to the asynchronous MQTT client and its event loop.
     /// The event loop is run on a background task.
     client: Option<Arc<AsyncClient>>,
     /// A signal for stopping the event loop
     stop_flag: Arc<Mutex<bool>>,
     /// A map of inflight requests (keyed by sequence_id).
-    inflight_commands: Arc<Mutex<HashMap<SmolStr, oneshot::Sender<Message>>>>,
+    inflight_commands: Arc<std::sync::Mutex<InflightCommands>>,
     /// Current sequence id.
-    sequence_id: Mutex<u64>,
+    sequence_id: Arc<Mutex<u64>>,
+    /// Printer state merged from `push_status` reports.
+    state: Arc<watch::Sender<PrinterState>>,
+    /// Events published by the background task.
+    events: broadcast::Sender<MqttEvent>,
+    /// How to reconnect after the connection is lost.
+    reconnect_policy: ReconnectPolicy,
+    /// How long to wait for a response to a command. `None` waits forever.
+    default_timeout: Option<Duration>,
 }
 
+type InflightCommands = HashMap<SmolStr, oneshot::Sender<Result<Message, MqttError>>>;
+
 impl MqttClient {
-    /// Create a new WatchClient.
+    /// Create a new WatchClient with the default settings.
+    ///
+    /// Use [`MqttClient::builder`] to change them.
     pub fn new(hostname: &str, access_code: &str, serial: &str) -> Self {
-        Self {
-            hostname: hostname.to_string(),
-            access_code: access_code.to_string(),
-            serial: serial.to_string(),
-            client: None,
-            stop_flag: Arc::new(Mutex::new(false)),
-            inflight_commands: Default::default(),
-            sequence_id: Mutex::new(0),
+        MqttClientBuilder::new(hostname, access_code, serial).build()
+    }
+
+    /// Create a builder for a client connecting to the given printer.
+    pub fn builder(
+        hostname: impl Into<String>,
+        access_code: impl Into<String>,
+        serial: impl Into<String>,
+    ) -> MqttClientBuilder {
+        MqttClientBuilder::new(hostname, access_code, serial)
+    }
+
+    /// The printer model, derived from its serial number, or from the modules reported by
+    /// [`get_version`](Self::get_version) if the serial number is not recognized.
+    pub fn model(&self) -> PrinterModel {
+        match PrinterModel::from_serial(&self.serial) {
+            PrinterModel::Unknown => self.detected_model,
+            model => model,
+        }
+    }
+
+    /// The features and limits of the printer, see [`model`](Self::model).
+    pub fn capabilities(&self) -> Capabilities {
+        self.model().capabilities()
+    }
+
+    /// Fail with [`MqttError::Unsupported`] unless `supported`.
+    fn require(&self, supported: bool, feature: &str) -> Result<(), MqttError> {
+        if supported {
+            Ok(())
+        } else {
+            Err(MqttError::Unsupported {
+                model: self.model(),
+                feature: feature.into(),
+            })
+        }
+    }
+
+    /// The global tray id of `location`, if it exists on the printer.
+    fn tray_id(&self, location: TrayLocation) -> Result<u8, MqttError> {
+        let tray_id = location.checked_tray_id()?;
+        if let TrayLocation::Ams { unit, .. } = location {
+            self.require(
+                self.capabilities().has_tray(location),
+                &format!("AMS unit {unit}"),
+            )?;
         }
+        Ok(tray_id)
+    }
+
+    /// Set how long to wait for a response to a command before failing with
+    /// [`MqttError::Timeout`]. `None` waits forever.
+    pub fn set_default_timeout(&mut self, timeout: Option<Duration>) {
+        self.default_timeout = timeout;
+    }
+
+    /// Set the policy used to reconnect after the connection to the printer is lost.
+    ///
+    /// Takes effect on the next call to [`MqttClient::start`].
+    pub fn set_reconnect_policy(&mut self, policy: ReconnectPolicy) {
+        self.reconnect_policy = policy;
     }
 
     /// Start the MQTT client.
@@ -132,23 +208,29 @@ impl MqttClient {
     pub async fn start(&mut self) -> Result<JoinHandle<()>> {
         // 1) Build MqttOptions
         let mut mqttoptions =
-            MqttOptions::new(DEFAULT_MQTT_ID, self.hostname.clone(), DEFAULT_MQTT_PORT);
+            MqttOptions::new(self.client_id.clone(), self.hostname.clone(), self.port);
 
         // Set username & password
-        mqttoptions.set_credentials(DEFAULT_MQTT_USERNAME, &self.access_code);
-        mqttoptions.set_keep_alive(Duration::from_secs(60));
+        mqttoptions.set_credentials(self.username.clone(), &self.access_code);
+        mqttoptions.set_keep_alive(self.keep_alive);
 
-        // 2) Configure TLS ignoring certificate validation
-        // rumqttc uses rustls internally. We'll supply a dangerous configuration.
-        let config: ClientConfig = ClientConfig::builder()
-            .dangerous()
-            .with_custom_certificate_verifier(Arc::new(RumqttcNoVerifier))
-            .with_no_client_auth();
-
-        mqttoptions.set_transport(Transport::Tls(TlsConfiguration::Rustls(Arc::new(config))));
+        // 2) Configure the transport
+        let transport = match &self.transport {
+            MqttTransport::Tls => {
+                // rumqttc uses rustls internally. We'll supply a configuration verifying the
+                // printer certificate according to the certificate policy.
+                let config = tls::rumqttc_client_config(&self.certificate_policy);
+                Transport::Tls(TlsConfiguration::Rustls(Arc::new(config)))
+            }
+            MqttTransport::Rustls(config) => {
+                Transport::Tls(TlsConfiguration::Rustls(Arc::clone(config)))
+            }
+            MqttTransport::Tcp => Transport::Tcp,
+        };
+        mqttoptions.set_transport(transport);
 
         // 3) Create the AsyncClient and EventLoop
-        let (client, mut event_loop) = AsyncClient::new(mqttoptions, 10);
+        let (client, mut event_loop) = AsyncClient::new(mqttoptions, self.capacity);
         let client = Arc::new(client);
         self.client = Some(Arc::clone(&client));
 
@@ -162,14 +244,19 @@ impl MqttClient {
         let stop_flag = self.stop_flag.clone();
 
         let serial = self.serial.clone();
+        let reconnect_policy = self.reconnect_policy.clone();
 
         let (connected_tx, connected_rx) = oneshot::channel();
 
         let handle = tokio::spawn({
             let inflight_commands = Arc::clone(&self.inflight_commands);
+            let sequence_id = Arc::clone(&self.sequence_id);
+            let state = Arc::clone(&self.state);
+            let events = self.events.clone();
 
             async move {
                 let mut connected_tx = Some(connected_tx);
+                let mut attempt = 0;
 
                 // We subscribe once we see a successful connection (Event::Connected).
                 // Then we listen for packets in a loop.
@@ -183,15 +270,25 @@ impl MqttClient {
                                             // "Connected" event
                                             // Subscribe to `device/{serial}/report`
                                             let topic = format!("device/{}/report", serial);
-                                            if let Err(e) = client.subscribe(topic.clone(), QoS::AtMostOnce).await {
-                                                eprintln!("Failed to subscribe to {}: {:?}", topic, e);
+                                            if let Err(e) = client.subscribe(topic, QoS::AtMostOnce).await {
+                                                let _ = events.send(MqttEvent::ClientError(Arc::new(e)));
                                                 break;
                                             }
 
                                             // Notify the main task that we are connected
                                             if let Some(tx) = connected_tx.take() {
                                                 tx.send(Ok(())).unwrap();
+                                            } else {
+                                                // We have reconnected, so ask for a full report to catch up on what we missed.
+                                                let command = push_all_command(next_sequence_id(&sequence_id).await);
+                                                let payload = serde_json::to_vec(&command).unwrap();
+                                                let topic = format!("device/{}/request", serial);
+                                                if let Err(e) = client.publish(topic, QoS::AtMostOnce, false, payload).await {
+                                                    let _ = events.send(MqttEvent::ClientError(Arc::new(e)));
+                                                }
                                             }
+                                            attempt = 0;
+                                            let _ = events.send(MqttEvent::ConnectionState(ConnectionState::Connected));
                                         }
                                         Packet::Publish(publish) => {
                                             // Check topic if it matches the one we subscribed
@@ -202,27 +299,38 @@ impl MqttClient {
                                             match serde_json::from_slice::<Message>(&payload) {
                                                 Ok(Message::Print(print)) if print.command == "push_status" => {
                                                     // Pushed message for which there is no inflight command.
-                                                    println!("Received pushed message from {topic}: {:?}", print);
+                                                    // Merge the report into the printer state.
+                                                    // Only wake up watchers if the report was merged.
+                                                    let mut merge_error = None;
+                                                    state.send_if_modified(|state| {
+                                                        merge_error = state.apply(&print, Utc::now()).err();
+                                                        merge_error.is_none()
+                                                    });
+                                                    if let Some(err) = merge_error {
+                                                        let _ = events.send(MqttEvent::Unparsed {
+                                                            topic: topic.into(),
+                                                            payload,
+                                                            error: Arc::new(err),
+                                                        });
+                                                    }
+
+                                                    let _ = events.send(MqttEvent::Status(print));
                                                 }
                                                 Ok(msg) => {
-                                                    // Handle the message here.
-                                                    let mut inflight_commands = Arc::clone(&inflight_commands).lock_owned().await;
-
-                                                    match inflight_commands.remove(msg.sequence_id()) {
-                                                        Some(inflight_command) => {
-                                                            println!("Received message from {topic}: {:?}", msg);
-
-                                                            // Send the response back to the command sender.
-                                                            inflight_command.send(msg).unwrap();
-                                                        }
-                                                        None => {
-                                                            eprintln!("Received message with unknown sequence_id: {msg:?}");
-                                                        }
-                                                    }
+                                                    let _ = events.send(MqttEvent::Response(msg.clone()));
 
+                                                    // Send the response back to the command sender, if it is still waiting for it.
+                                                    let inflight_command = inflight_commands.lock().unwrap().remove(msg.sequence_id());
+                                                    if let Some(inflight_command) = inflight_command {
+                                                        let _ = inflight_command.send(Ok(msg));
+                                                    }
                                                 }
                                                 Err(err) => {
-                                                    eprintln!("Failed to parse MQTT payload from {topic}: {:?} (payload: {})", err, String::from_utf8_lossy(&payload));
+                                                    let _ = events.send(MqttEvent::Unparsed {
+                                                        topic: topic.into(),
+                                                        payload,
+                                                        error: Arc::new(err),
+                                                    });
                                                 }
                                             }
                                         }
@@ -233,32 +341,45 @@ impl MqttClient {
                                     // Outgoing events, usually not needed to handle
                                 }
                                 Err(e) => {
-                                    eprintln!("MQTT error: {:?}", e);
+                                    let e = Arc::new(e);
+                                    let _ = events.send(MqttEvent::Error(Arc::clone(&e)));
+                                    let _ = events.send(MqttEvent::ConnectionState(ConnectionState::Disconnected));
 
+                                    // Responses to inflight commands will never arrive.
+                                    fail_inflight_commands(&inflight_commands);
+
+                                    // The initial connection failed, let the caller decide what to do.
                                     if let Some(tx) = connected_tx.take() {
                                         tx.send(Err(e)).unwrap();
+                                        break;
+                                    }
+
+                                    attempt += 1;
+                                    let Some(delay) = reconnect_policy.delay(attempt) else {
+                                        // We are out of attempts.
+                                        break;
+                                    };
+                                    let _ = events.send(MqttEvent::ConnectionState(ConnectionState::Reconnecting { attempt, delay }));
+
+                                    // The next poll reconnects.
+                                    tokio::select! {
+                                        _ = tokio::time::sleep(delay) => {}
+                                        _ = wait_for_stop(&stop_flag) => break,
                                     }
-                                    break;
                                 }
                             }
                         }
                         // If `stop_flag` is set to true, break out
-                        _ = async {
-                            let mut interval = tokio::time::interval(Duration::from_millis(500));
-                            loop {
-                                interval.tick().await;
-                                if *stop_flag.lock().await {
-                                    break;
-                                }
-                            }
-                        } => {
+                        _ = wait_for_stop(&stop_flag) => {
                             // We are asked to stop
+                            let _ = events.send(MqttEvent::ConnectionState(ConnectionState::Disconnected));
                             break;
                         }
                     }
                 }
 
                 // We are done: attempt a graceful shutdown
+                fail_inflight_commands(&inflight_commands);
                 let _ = client.disconnect().await;
             }
         });
@@ -272,6 +393,46 @@ impl MqttClient {
         Ok(handle)
     }
 
+    /// Watch the printer state.
+    ///
+    /// The state is updated by the background task each time a `push_status` report arrives.
+    /// Call [`MqttClient::push_all`] once connected to receive a full snapshot first.
+    pub fn state(&self) -> watch::Receiver<PrinterState> {
+        self.state.subscribe()
+    }
+
+    /// Subscribe to events received by the background task.
+    ///
+    /// Only events published after this call are delivered, so subscribe before calling
+    /// [`MqttClient::start`] to observe the initial connection.
+    pub fn subscribe(&self) -> impl Stream<Item = MqttEvent> + Send + Unpin + 'static {
+        BroadcastStream::new(self.events.subscribe()).map(|event| match event {
+            Ok(event) => event,
+            Err(BroadcastStreamRecvError::Lagged(count)) => MqttEvent::Lagged(count),
+        })
+    }
+
+    /// Subscribe to changes of the active HMS errors.
+    ///
+    /// Errors that are already active are reported as raised first. Errors cleared by a report
+    /// come before the errors it raises, each ordered by code. Errors raised and cleared between
+    /// two reports the subscriber did not poll in time may be missed.
+    pub fn hms_events(&self) -> impl Stream<Item = HmsEvent> + Send + Unpin + 'static {
+        let mut active = BTreeSet::new();
+        let states = WatchStream::new(self.state());
+        futures_util::StreamExt::flat_map(states, move |state| {
+            let current: BTreeSet<Hms> = state.status().hms.iter().flatten().copied().collect();
+            let mut events: Vec<_> = active
+                .difference(&current)
+                .copied()
+                .map(HmsEvent::Cleared)
+                .collect();
+            events.extend(current.difference(&active).copied().map(HmsEvent::Raised));
+            active = current;
+            futures_util::stream::iter(events)
+        })
+    }
+
     /// Stop the MQTT loop and disconnect.
     pub async fn stop(&mut self) -> Result<()> {
         // Signal the background task to end
@@ -291,22 +452,30 @@ impl MqttClient {
         let topic = format!("device/{}/request", self.serial);
         let qos = QoS::AtMostOnce;
 
-        eprintln!(
-            "Publishing command to {}: {}",
-            topic,
-            String::from_utf8_lossy(&payload)
-        );
-
         let client = Arc::clone(self.client.as_ref().unwrap());
         client.publish(topic, qos, false, payload).await?;
 
         Ok(())
     }
 
-    /// Send a command to the printer.
+    /// Send a command to the printer and wait for the response for at most the default timeout.
     pub(crate) async fn send_raw_command_and_wait(
         &mut self,
         command: Command,
+    ) -> Result<Message, MqttError> {
+        let timeout = self.default_timeout;
+        self.send_raw_command_and_wait_timeout(command, timeout)
+            .await
+    }
+
+    /// Send a command to the printer and wait for the response for at most `timeout`, overriding
+    /// the default timeout. `None` waits forever.
+    ///
+    /// Dropping the returned future stops waiting for the response.
+    pub(crate) async fn send_raw_command_and_wait_timeout(
+        &mut self,
+        command: Command,
+        timeout: Option<Duration>,
     ) -> Result<Message, MqttError> {
         // Serialize the command
         let payload = serde_json::to_vec(&command)?;
@@ -323,32 +492,34 @@ impl MqttClient {
         let client = Arc::clone(self.client.as_ref().unwrap());
 
         // Store the command in the inflight_commands map
-        {
-            let mut inflight_commands = self.inflight_commands.lock().await;
-            inflight_commands.insert(sequence_id, tx);
-        }
-
-        eprintln!(
-            "Publishing command to {}: {}",
-            topic,
-            String::from_utf8_lossy(&payload)
-        );
+        self.inflight_commands
+            .lock()
+            .unwrap()
+            .insert(sequence_id.clone(), tx);
+        let _guard = InflightGuard {
+            inflight_commands: Arc::clone(&self.inflight_commands),
+            sequence_id,
+        };
 
         // Publish the command to the MQTT broker and wait for the response to arrive in the oneshot channel (rx) we created.
         client.publish(topic, qos, false, payload).await?;
 
         // Wait for the response to arrive in the oneshot channel.
-        let response = rx.await.unwrap();
-        Ok(response)
+        let response = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, rx)
+                .await
+                .map_err(|_| MqttError::Timeout)?,
+            None => rx.await,
+        };
+        response.map_err(|_| MqttError::Disconnected)?
     }
 
     async fn send_command_and_wait<T>(&mut self, command: Command) -> Result<T, MqttError>
     where
-        T: TryFrom<Message>,
-        <T as TryFrom<Message>>::Error: std::fmt::Debug,
+        T: TryFrom<Message, Error = TryFromMessageError>,
     {
         let message = self.send_raw_command_and_wait(command).await?;
-        Ok(T::try_from(message).unwrap())
+        Ok(T::try_from(message)?)
     }
 
     /// Get the version of the printer.
@@ -359,24 +530,35 @@ impl MqttClient {
                 command: InfoCommand::GetVersion,
             },
         };
-        let result = self.send_command_and_wait(command).await?;
-        Ok(result)
+        let info: Info = self.send_command_and_wait(command).await?;
+        self.detected_model = info.model();
+        Ok(info)
     }
 
     pub async fn push_all(&mut self) -> Result<(), MqttError> {
-        let command = Command::Pushing {
-            pushing: PushingPayload {
-                sequence_id: self.next_sequence_id().await,
-                command: PushingCommand::PushAll {
-                    push_target: 1,
-                    version: 1,
-                },
-            },
-        };
+        let command = push_all_command(self.next_sequence_id().await);
         self.send_raw_command(command).await
     }
 
-    /// Request for printer to push all data to the client.
+    /// Request a full report with `pushall` and wait until it is merged into the state, for at
+    /// most the default timeout.
+    pub async fn refresh_state(&mut self) -> Result<PrinterState, MqttError> {
+        let mut state = self.state();
+        let previous = state.borrow_and_update().last_full_report();
+        self.push_all().await?;
+        wait_for_state(
+            &mut state,
+            |state| state.last_full_report() != previous,
+            self.default_timeout,
+        )
+        .await?;
+        let state = state.borrow().clone();
+        Ok(state)
+    }
+
+    /// Request the pressure advance profiles of `filament_id`, or of all filaments if empty, for
+    /// `nozzle_diameter`. See [`pressure_advance_profiles`](Self::pressure_advance_profiles) for
+    /// the decoded profiles.
     pub async fn extrusion_calibration_get(
         &mut self,
         filament_id: impl Into<SmolStr>,
@@ -385,29 +567,699 @@ impl MqttClient {
         let command = Command::Print {
             print: PrintPayload {
                 sequence_id: self.next_sequence_id().await,
-                command: PrintCommand::ExtrusionCalibrationGet {
-                    filament_id: filament_id.into(),
+                command: PrintCommand::extrusion_calibration_get(filament_id, nozzle_diameter),
+            },
+        };
+        self.send_raw_command_and_wait(command).await
+    }
+
+    /// The pressure advance (K-factor) profiles stored on the printer for `filament_id`, or for
+    /// all filaments if empty, and `nozzle_diameter`, e.g. `"0.4"`.
+    pub async fn pressure_advance_profiles(
+        &mut self,
+        filament_id: impl Into<SmolStr>,
+        nozzle_diameter: impl Into<SmolStr>,
+    ) -> Result<Vec<PressureAdvance>, MqttError> {
+        let message = self
+            .extrusion_calibration_get(filament_id, nozzle_diameter)
+            .await?;
+        let response = Print::try_from(message)?;
+        Ok(response.filaments.unwrap_or_default())
+    }
+
+    /// Create a pressure advance profile for `nozzle_diameter`, or edit it if its `cali_idx` is
+    /// not [`NEW_PROFILE_INDEX`](command::calibration::NEW_PROFILE_INDEX).
+    pub async fn set_pressure_advance(
+        &mut self,
+        nozzle_diameter: impl Into<SmolStr>,
+        profile: PressureAdvance,
+    ) -> Result<Print, MqttError> {
+        profile.validate()?;
+        let command = Command::Print {
+            print: PrintPayload {
+                sequence_id: self.next_sequence_id().await,
+                command: PrintCommand::ExtrusionCalibrationSet {
                     nozzle_diameter: nozzle_diameter.into(),
+                    filaments: vec![profile],
                 },
             },
         };
-        self.send_raw_command_and_wait(command).await
+        self.send_command_and_wait(command).await
+    }
+
+    /// Use `profile` for the filament of the tray at `location`.
+    pub async fn select_pressure_advance(
+        &mut self,
+        location: TrayLocation,
+        profile: &PressureAdvance,
+        nozzle_diameter: impl Into<SmolStr>,
+    ) -> Result<Print, MqttError> {
+        let command = Command::Print {
+            print: PrintPayload {
+                sequence_id: self.next_sequence_id().await,
+                command: PrintCommand::ExtrusionCalibrationSelect {
+                    tray_id: self.tray_id(location)?,
+                    cali_idx: profile.cali_idx,
+                    filament_id: profile.filament_id.clone(),
+                    nozzle_diameter: nozzle_diameter.into(),
+                },
+            },
+        };
+        self.send_command_and_wait(command).await
+    }
+
+    /// Delete `profile` from the printer.
+    pub async fn delete_pressure_advance(
+        &mut self,
+        profile: &PressureAdvance,
+        nozzle_diameter: impl Into<SmolStr>,
+    ) -> Result<Print, MqttError> {
+        if profile.cali_idx < 0 {
+            return Err(MqttError::InvalidArgument(
+                "the profile is not stored on the printer".into(),
+            ));
+        }
+        let command = Command::Print {
+            print: PrintPayload {
+                sequence_id: self.next_sequence_id().await,
+                command: PrintCommand::ExtrusionCalibrationDelete {
+                    cali_idx: profile.cali_idx,
+                    filament_id: profile.filament_id.clone(),
+                    nozzle_diameter: nozzle_diameter.into(),
+                },
+            },
+        };
+        self.send_command_and_wait(command).await
+    }
+
+    /// Run the calibrations selected in `options`.
+    ///
+    /// Fails with [`MqttError::Busy`] while a print is running or paused.
+    pub async fn start_calibration(
+        &mut self,
+        options: CalibrationOptions,
+    ) -> Result<Print, MqttError> {
+        if options.is_empty() {
+            return Err(MqttError::InvalidArgument("no calibration selected".into()));
+        }
+        if options.lidar {
+            self.require(self.capabilities().lidar, "Micro Lidar calibration")?;
+        }
+        self.ensure_not_printing()?;
+        let command = Command::Print {
+            print: PrintPayload {
+                sequence_id: self.next_sequence_id().await,
+                command: PrintCommand::Calibration { option: options },
+            },
+        };
+        self.send_command_and_wait(command).await
+    }
+
+    /// Print a flow rate calibration pattern.
+    ///
+    /// Fails with [`MqttError::Busy`] while a print is running or paused.
+    pub async fn start_flow_rate_calibration(
+        &mut self,
+        calibration: FlowRateCalibration,
+    ) -> Result<Print, MqttError> {
+        let Some(location) = TrayLocation::from_tray_id(calibration.tray_id) else {
+            return Err(MqttError::InvalidArgument(format_smolstr!(
+                "tray {} does not exist",
+                calibration.tray_id
+            )));
+        };
+        self.tray_id(location)?;
+        self.ensure_not_printing()?;
+        let command = Command::Print {
+            print: PrintPayload {
+                sequence_id: self.next_sequence_id().await,
+                command: PrintCommand::FlowRateCalibration(calibration),
+            },
+        };
+        self.send_command_and_wait(command).await
+    }
+
+    /// Start printing a `.3mf` file stored on the printer. The options are validated first and
+    /// [`MqttError::InvalidArgument`] is returned without contacting the printer if they are
+    /// invalid.
+    ///
+    /// The response only acknowledges the command. Watch [`state`](Self::state) to follow the
+    /// print job.
+    pub async fn start_print(&mut self, project: ProjectFile) -> Result<Print, MqttError> {
+        project.validate()?;
+        let command = Command::Print {
+            print: PrintPayload {
+                sequence_id: self.next_sequence_id().await,
+                command: PrintCommand::ProjectFile(Box::new(project)),
+            },
+        };
+        self.send_command_and_wait(command).await
+    }
+
+    /// Change the print speed and wait for a report confirming it, for at most the default
+    /// timeout.
+    pub async fn set_print_speed(&mut self, level: SpeedLevel) -> Result<(), MqttError> {
+        if let SpeedLevel::Unknown(level) = level {
+            return Err(MqttError::InvalidArgument(format_smolstr!(
+                "unknown speed level {level}"
+            )));
+        }
+        let command = Command::Print {
+            print: PrintPayload {
+                sequence_id: self.next_sequence_id().await,
+                command: PrintCommand::PrintSpeed { param: level },
+            },
+        };
+        self.send_command_and_wait_for_status(command, |status| status.spd_lvl == Some(level))
+            .await
+    }
+
+    /// Send a command and wait until a report received afterwards satisfies `confirmed`, for at
+    /// most the default timeout. Used for commands the printer does not answer directly.
+    pub(crate) async fn send_command_and_wait_for_status(
+        &mut self,
+        command: Command,
+        confirmed: impl FnMut(&Print) -> bool,
+    ) -> Result<(), MqttError> {
+        let mut state = self.state();
+        state.mark_unchanged();
+        self.send_raw_command(command).await?;
+        wait_for_status(&mut state, confirmed, self.default_timeout).await
+    }
+
+    /// Stop printing the objects with the given ids in the current job, and wait until the printer
+    /// reports them as skipped in [`Print::s_obj`].
+    ///
+    /// Ids are the `identify_id` of the objects in the plate metadata
+    /// (`Metadata/slice_info.config`) of the project file being printed. The printer does not
+    /// report the objects of the job, so the ids cannot be checked against them: the firmware
+    /// ignores unknown ids and the wait then fails with [`MqttError::Timeout`].
+    /// The printer must be running or paused, and objects cannot be skipped twice.
+    pub async fn skip_object_ids(&mut self, ids: &[u64]) -> Result<(), MqttError> {
+        if ids.is_empty() {
+            return Err(MqttError::InvalidArgument("no object to skip".into()));
+        }
+        {
+            let state = self.state.borrow();
+            let status = state.status();
+            match &status.gcode_state {
+                Some(GcodeState::Running | GcodeState::Pause) => {}
+                state => {
+                    return Err(MqttError::InvalidArgument(format_smolstr!(
+                        "no print job to skip objects in ({})",
+                        state.as_ref().map_or("unknown", |state| state.as_str())
+                    )));
+                }
+            }
+            let skipped = status.s_obj.as_deref().unwrap_or_default();
+            if let Some(id) = ids.iter().find(|id| skipped.contains(id)) {
+                return Err(MqttError::InvalidArgument(format_smolstr!(
+                    "object {id} is already skipped"
+                )));
+            }
+        }
+
+        let obj_list = ids.to_vec();
+        let command = Command::Print {
+            print: PrintPayload {
+                sequence_id: self.next_sequence_id().await,
+                command: PrintCommand::SkipObjects {
+                    obj_list: obj_list.clone(),
+                },
+            },
+        };
+        self.send_command_and_wait_for_status(command, |status| {
+            status
+                .s_obj
+                .as_ref()
+                .is_some_and(|skipped| obj_list.iter().all(|id| skipped.contains(id)))
+        })
+        .await
+    }
+
+    /// Set the target temperature of the nozzle. See [`set_temperature`](Self::set_temperature).
+    pub async fn set_nozzle_temperature(
+        &mut self,
+        target: u16,
+        tolerance: Option<f64>,
+    ) -> Result<(), MqttError> {
+        self.set_temperature(Heater::Nozzle, target, tolerance)
+            .await
+    }
+
+    /// Set the target temperature of the bed. See [`set_temperature`](Self::set_temperature).
+    pub async fn set_bed_temperature(
+        &mut self,
+        target: u16,
+        tolerance: Option<f64>,
+    ) -> Result<(), MqttError> {
+        self.set_temperature(Heater::Bed, target, tolerance).await
+    }
+
+    /// Set the target temperature of the chamber, on printers with a chamber heater. See
+    /// [`set_temperature`](Self::set_temperature).
+    pub async fn set_chamber_temperature(
+        &mut self,
+        target: u16,
+        tolerance: Option<f64>,
+    ) -> Result<(), MqttError> {
+        self.set_temperature(Heater::Chamber, target, tolerance)
+            .await
     }
 
-    /// Set the lights on or off on the printer.
+    /// Set the target temperature of `heater` in Celsius, 0 to turn it off.
+    ///
+    /// The target is checked against the limits of the printer [`model`](Self::model). With a
+    /// `tolerance`, this then waits until the reported temperature is within `tolerance` of the
+    /// target, failing with [`MqttError::Timeout`] after the
+    /// [default timeout](Self::set_default_timeout). Heating can take minutes, longer than the
+    /// default, so raise the timeout before waiting on a cold heater. Cooling down to room
+    /// temperature takes far longer still, so a `tolerance` is rejected when turning the heater
+    /// off.
+    pub async fn set_temperature(
+        &mut self,
+        heater: Heater,
+        target: u16,
+        tolerance: Option<f64>,
+    ) -> Result<(), MqttError> {
+        let model = self.model();
+        match self.capabilities().max_temperature(heater) {
+            None => {
+                return Err(MqttError::Unsupported {
+                    model,
+                    feature: format_smolstr!("a {} heater", heater.name()),
+                });
+            }
+            Some(max) if target > max => {
+                return Err(MqttError::InvalidArgument(format_smolstr!(
+                    "{} temperature {target} exceeds the {model} limit of {max}",
+                    heater.name()
+                )));
+            }
+            Some(_) => {}
+        }
+        match tolerance {
+            Some(_) if target == 0 => {
+                return Err(MqttError::InvalidArgument(format_smolstr!(
+                    "cannot wait for the {} heater to cool down to 0",
+                    heater.name()
+                )));
+            }
+            Some(tolerance) if !(tolerance >= 0.0 && tolerance.is_finite()) => {
+                return Err(MqttError::InvalidArgument(format_smolstr!(
+                    "tolerance {tolerance} must be a finite number of degrees, at least 0"
+                )));
+            }
+            _ => {}
+        }
+
+        let mut state = self.state();
+        self.send_gcode(gcode::set_temperature(heater, target))
+            .await?;
+
+        let Some(tolerance) = tolerance else {
+            return Ok(());
+        };
+        let reached = move |status: &Print| {
+            heater
+                .temperature(status)
+                .is_some_and(|current| (current - f64::from(target)).abs() <= tolerance)
+        };
+        if reached(state.borrow_and_update().status()) {
+            return Ok(());
+        }
+        wait_for_status(&mut state, reached, self.default_timeout).await
+    }
+
+    /// Set the speed of `fan` in percent.
+    pub async fn set_fan_speed(&mut self, fan: Fan, percent: u8) -> Result<(), MqttError> {
+        if !self.capabilities().has_fan(fan) {
+            return Err(MqttError::Unsupported {
+                model: self.model(),
+                feature: format_smolstr!("a {} fan", fan.name()),
+            });
+        }
+        if percent > 100 {
+            return Err(MqttError::InvalidArgument(format_smolstr!(
+                "fan speed {percent}% exceeds 100%"
+            )));
+        }
+        let param = gcode::set_fan_speed(fan, percent).ok_or_else(|| {
+            MqttError::InvalidArgument(format_smolstr!(
+                "the {} fan is controlled by the printer",
+                fan.name()
+            ))
+        })?;
+        self.send_gcode(param).await
+    }
+
+    /// Home `axes`, or all axes if empty.
+    ///
+    /// Fails with [`MqttError::Busy`] while a print is running or paused.
+    pub async fn home(&mut self, axes: &[Axis]) -> Result<(), MqttError> {
+        self.ensure_not_printing()?;
+        self.send_gcode(GcodeBuilder::new().home(axes).build())
+            .await
+    }
+
+    /// Move the toolhead or bed along `moves`, at `feedrate` mm/min if given.
+    ///
+    /// Absolute positions must lie within the build volume of the printer and relative moves may
+    /// not be longer than it. The position of the toolhead is not reported, so relative moves can
+    /// still hit an endstop. Fails with [`MqttError::Busy`] while a print is running or paused.
+    pub async fn jog(
+        &mut self,
+        positioning: Positioning,
+        moves: &[(Axis, f64)],
+        feedrate: Option<u32>,
+    ) -> Result<(), MqttError> {
+        if moves.is_empty() {
+            return Err(MqttError::InvalidArgument("no axis to move".into()));
+        }
+        let model = self.model();
+        let capabilities = self.capabilities();
+        for &(axis, value) in moves {
+            let limit = capabilities.travel_limit(axis);
+            let allowed = match positioning {
+                Positioning::Absolute => (0.0..=limit).contains(&value),
+                Positioning::Relative => (-limit..=limit).contains(&value),
+            };
+            if !allowed {
+                return Err(MqttError::InvalidArgument(format_smolstr!(
+                    "{} move of {value} mm exceeds the {model} travel of {limit} mm",
+                    axis.name()
+                )));
+            }
+        }
+        validate_feedrate(feedrate)?;
+        self.ensure_not_printing()?;
+
+        let mut gcode = GcodeBuilder::new().positioning(positioning);
+        if let Some(feedrate) = feedrate {
+            gcode = gcode.feedrate(feedrate);
+        }
+        gcode = gcode.travel(moves);
+        // Leave the printer in absolute positioning, which is what the firmware expects.
+        if positioning == Positioning::Relative {
+            gcode = gcode.positioning(Positioning::Absolute);
+        }
+        self.send_gcode(gcode.build()).await
+    }
+
+    /// Extrude `length` mm of filament, or retract it if negative, at `feedrate` mm/min if given.
+    ///
+    /// The printer refuses to extrude while the nozzle is cold. Fails with [`MqttError::Busy`]
+    /// while a print is running or paused.
+    pub async fn extrude(&mut self, length: f64, feedrate: Option<u32>) -> Result<(), MqttError> {
+        if !(-MAX_EXTRUDE_LENGTH..=MAX_EXTRUDE_LENGTH).contains(&length) {
+            return Err(MqttError::InvalidArgument(format_smolstr!(
+                "extrusion of {length} mm exceeds {MAX_EXTRUDE_LENGTH} mm"
+            )));
+        }
+        validate_feedrate(feedrate)?;
+        self.ensure_not_printing()?;
+
+        let mut gcode = GcodeBuilder::new().relative_extrusion();
+        if let Some(feedrate) = feedrate {
+            gcode = gcode.feedrate(feedrate);
+        }
+        // Leave the printer in absolute extrusion, like `jog` does for positioning.
+        gcode = gcode.extrude(length).absolute_extrusion();
+        self.send_gcode(gcode.build()).await
+    }
+
+    /// Fail with [`MqttError::Busy`] if the last report shows a print in progress. A paused print
+    /// counts too: the firmware resumes from where it stopped without re-homing, so moving the
+    /// toolhead in between would ruin the print.
+    fn ensure_not_printing(&self) -> Result<(), MqttError> {
+        match &self.state.borrow().status().gcode_state {
+            Some(state @ (GcodeState::Prepare | GcodeState::Running | GcodeState::Pause)) => {
+                Err(MqttError::Busy(state.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn send_gcode(&mut self, param: SmolStr) -> Result<(), MqttError> {
+        let command = Command::Print {
+            print: PrintPayload {
+                sequence_id: self.next_sequence_id().await,
+                command: PrintCommand::GcodeLine { param },
+            },
+        };
+        self.send_raw_command(command).await
+    }
+
+    /// Load the filament of the tray at `location`, heating the nozzle to `temperature` (in
+    /// Celsius) first. Unloads the current filament if needed.
+    pub async fn load_tray(
+        &mut self,
+        location: TrayLocation,
+        temperature: u16,
+    ) -> Result<Print, MqttError> {
+        self.tray_id(location)?;
+        let command = Command::Print {
+            print: PrintPayload {
+                sequence_id: self.next_sequence_id().await,
+                command: PrintCommand::change_filament(location, temperature)?,
+            },
+        };
+        self.send_command_and_wait(command).await
+    }
+
+    /// Retract the loaded filament.
+    pub async fn unload_filament(&mut self) -> Result<Print, MqttError> {
+        let command = Command::Print {
+            print: PrintPayload {
+                sequence_id: self.next_sequence_id().await,
+                command: PrintCommand::UnloadFilament,
+            },
+        };
+        self.send_command_and_wait(command).await
+    }
+
+    /// Resume, reset or pause the AMS, e.g. after a filament runout.
+    pub async fn ams_control(&mut self, action: AmsControl) -> Result<Print, MqttError> {
+        let command = Command::Print {
+            print: PrintPayload {
+                sequence_id: self.next_sequence_id().await,
+                command: PrintCommand::AmsControl { param: action },
+            },
+        };
+        self.send_command_and_wait(command).await
+    }
+
+    /// Set the filament of the tray at `location`, e.g. for spools without an RFID tag.
+    pub async fn set_tray_filament(
+        &mut self,
+        location: TrayLocation,
+        filament: &TrayFilament,
+    ) -> Result<Print, MqttError> {
+        filament.validate()?;
+        self.tray_id(location)?;
+        let command = Command::Print {
+            print: PrintPayload {
+                sequence_id: self.next_sequence_id().await,
+                command: PrintCommand::filament_setting(location, filament)?,
+            },
+        };
+        self.send_command_and_wait(command).await
+    }
+
+    /// Enable or disable recording prints to the SD card, and wait until the printer reports it.
+    pub async fn set_camera_recording(&mut self, enabled: bool) -> Result<(), MqttError> {
+        let control = Toggle::from(enabled);
+        let confirmed = Some(control.clone());
+        self.send_camera_command(CameraCommand::RecordSet { control }, |ipcam| {
+            ipcam.ipcam_record == confirmed
+        })
+        .await
+    }
+
+    /// Enable or disable timelapse recording, and wait until the printer reports it.
+    pub async fn set_timelapse(&mut self, enabled: bool) -> Result<(), MqttError> {
+        let control = Toggle::from(enabled);
+        let confirmed = Some(control.clone());
+        self.send_camera_command(CameraCommand::Timelapse { control }, |ipcam| {
+            ipcam.timelapse == confirmed
+        })
+        .await
+    }
+
+    /// Set the resolution of the camera, and wait until the printer reports it. Only the X1
+    /// series supports changing it.
+    pub async fn set_camera_resolution(
+        &mut self,
+        resolution: CameraResolution,
+    ) -> Result<(), MqttError> {
+        if let CameraResolution::Unknown(resolution) = resolution {
+            return Err(MqttError::InvalidArgument(format_smolstr!(
+                "unknown camera resolution {resolution:?}"
+            )));
+        }
+        self.require(
+            self.capabilities().camera == CameraStream::Rtsp,
+            "changing the camera resolution",
+        )?;
+        let confirmed = Some(resolution.clone());
+        self.send_camera_command(CameraCommand::ResolutionSet { resolution }, |ipcam| {
+            ipcam.resolution == confirmed
+        })
+        .await
+    }
+
+    async fn send_camera_command(
+        &mut self,
+        command: CameraCommand,
+        mut confirmed: impl FnMut(&Ipcam) -> bool,
+    ) -> Result<(), MqttError> {
+        let command = Command::Camera {
+            camera: CameraPayload {
+                sequence_id: self.next_sequence_id().await,
+                command,
+            },
+        };
+        self.send_command_and_wait_for_status(command, |status| {
+            status.ipcam.as_ref().is_some_and(&mut confirmed)
+        })
+        .await
+    }
+
+    /// Enable or disable each detector of the AI print monitoring, and wait until the printer
+    /// reports the settings in [`Print::xcam`].
+    ///
+    /// First layer inspection is left alone on printers without a Micro Lidar. Detectors the
+    /// firmware does not report are not waited for.
+    pub async fn configure_ai_monitoring(
+        &mut self,
+        config: &AiMonitoringConfig,
+    ) -> Result<(), MqttError> {
+        if let Some(Sensitivity::Unknown(level)) = &config.spaghetti {
+            return Err(MqttError::InvalidArgument(format_smolstr!(
+                "unknown sensitivity {level:?}"
+            )));
+        }
+        let lidar = self.capabilities().lidar;
+        if config.first_layer_inspection {
+            self.require(lidar, "first layer inspection")?;
+        }
+        let controls: Vec<XcamControl> = config
+            .controls()
+            .into_iter()
+            .filter(|control| lidar || control.module_name != XcamModule::FirstLayerInspector)
+            .collect();
+
+        let mut state = self.state();
+        state.mark_unchanged();
+        for control in &controls {
+            let command = Command::Xcam {
+                xcam: XcamPayload {
+                    sequence_id: self.next_sequence_id().await,
+                    command: XcamCommand::ControlSet(control.clone()),
+                },
+            };
+            self.send_raw_command(command).await?;
+        }
+        let confirmed = |status: &Print| {
+            status.xcam.as_ref().is_some_and(|xcam| {
+                controls.iter().all(|control| {
+                    xcam.enabled(control.module_name)
+                        .is_none_or(|enabled| enabled == control.control)
+                })
+            })
+        };
+        wait_for_status(&mut state, confirmed, self.default_timeout).await
+    }
+
+    /// Set the chamber light on or off on the printer.
     pub async fn set_led(&mut self, on: bool) -> Result<System, MqttError> {
         let led_mode = if on { LedMode::On } else { LedMode::Off };
+        self.set_led_pattern(LedCtrl::new(LedNode::ChamberLight, led_mode))
+            .await
+    }
+
+    /// Turn a light on, off or make it flash, see [`LedCtrl::flashing`].
+    ///
+    /// The response only acknowledges the command, the resulting state is reported in
+    /// [`Print::lights_report`], see [`Print::light_mode`].
+    pub async fn set_led_pattern(&mut self, led: LedCtrl) -> Result<System, MqttError> {
+        if !self.capabilities().has_light(led.led_node) {
+            return Err(MqttError::Unsupported {
+                model: self.model(),
+                feature: format_smolstr!("the {} LED", led.led_node.as_str()),
+            });
+        }
+        if led.led_mode == LedMode::Flashing && led.led_on_time == 0 {
+            return Err(MqttError::InvalidArgument(
+                "flashing requires a non-zero on time".into(),
+            ));
+        }
+        let command = Command::System {
+            system: SystemPayload {
+                sequence_id: self.next_sequence_id().await,
+                command: SystemCommand::LedCtrl(led),
+            },
+        };
+        self.send_command_and_wait(command).await
+    }
+
+    /// Get the accessories of the printer, such as its nozzle. The response holds
+    /// [`SystemResponse::GetAccessories`](message::system::SystemResponse::GetAccessories).
+    pub async fn get_accessories(&mut self) -> Result<System, MqttError> {
+        let command = Command::System {
+            system: SystemPayload {
+                sequence_id: self.next_sequence_id().await,
+                command: SystemCommand::GetAccessories {
+                    accessory_type: AccessoryType::None,
+                },
+            },
+        };
+        self.send_command_and_wait(command).await
+    }
+
+    /// Get the LAN access code of the printer, as shown on its screen.
+    pub async fn get_access_code(&mut self) -> Result<SmolStr, MqttError> {
+        let command = Command::System {
+            system: SystemPayload {
+                sequence_id: self.next_sequence_id().await,
+                command: SystemCommand::GetAccessCode,
+            },
+        };
+        let response: System = self.send_command_and_wait(command).await?;
+        match response.command {
+            SystemResponse::GetAccessCode(AccessCode { access_code, .. }) => Ok(access_code),
+            _ => Err(TryFromMessageError::new(Message::System(response)).into()),
+        }
+    }
+
+    /// Tell the printer which nozzle is installed, after swapping the hotend. `diameter` is in mm
+    /// and must be one of [`NOZZLE_DIAMETERS`], up to rounding errors such as `0.1 * 4.0`.
+    pub async fn set_nozzle(
+        &mut self,
+        diameter: f64,
+        nozzle_type: NozzleType,
+    ) -> Result<System, MqttError> {
+        let Some(diameter) = NOZZLE_DIAMETERS
+            .into_iter()
+            .find(|known| (known - diameter).abs() < 1e-6)
+        else {
+            return Err(MqttError::InvalidArgument(format_smolstr!(
+                "unsupported nozzle diameter {diameter} mm"
+            )));
+        };
+        if let NozzleType::Unknown(nozzle_type) = nozzle_type {
+            return Err(MqttError::InvalidArgument(format_smolstr!(
+                "unknown nozzle type {nozzle_type:?}"
+            )));
+        }
         let command = Command::System {
             system: SystemPayload {
                 sequence_id: self.next_sequence_id().await,
-                command: SystemCommand::LedCtrl(LedCtrl {
-                    led_node: LedNode::ChamberLight,
-                    led_mode,
-                    led_on_time: 500,
-                    led_off_time: 500,
-                    loop_times: 0,
-                    interval_time: 0,
-                }),
+                command: SystemCommand::SetAccessories {
+                    accessory_type: AccessoryType::Nozzle,
+                    nozzle_diameter: diameter,
+                    nozzle_type,
+                },
             },
         };
         self.send_command_and_wait(command).await
@@ -415,9 +1267,782 @@ impl MqttClient {
 
     /// Get the next sequence id.
     pub(crate) async fn next_sequence_id(&self) -> SmolStr {
-        let mut sequence_id = self.sequence_id.lock().await;
-        let result = format_smolstr!("{}", *sequence_id);
-        *sequence_id += 1;
-        result
+        next_sequence_id(&self.sequence_id).await
+    }
+}
+
+/// Take the next sequence id from `counter`, shared with the background task.
+async fn next_sequence_id(counter: &Mutex<u64>) -> SmolStr {
+    let mut sequence_id = counter.lock().await;
+    let result = format_smolstr!("{}", *sequence_id);
+    *sequence_id += 1;
+    result
+}
+
+/// Request for printer to push all data to the client.
+fn push_all_command(sequence_id: SmolStr) -> Command {
+    Command::Pushing {
+        pushing: PushingPayload {
+            sequence_id,
+            command: PushingCommand::PushAll {
+                push_target: 1,
+                version: 1,
+            },
+        },
+    }
+}
+
+/// Wait until a new report satisfies `confirmed`, for at most `timeout`.
+async fn wait_for_status(
+    state: &mut watch::Receiver<PrinterState>,
+    mut confirmed: impl FnMut(&Print) -> bool,
+    timeout: Option<Duration>,
+) -> Result<(), MqttError> {
+    wait_for_state(state, |state| confirmed(state.status()), timeout).await
+}
+
+/// Wait until the state after a new report satisfies `confirmed`, for at most `timeout`.
+async fn wait_for_state(
+    state: &mut watch::Receiver<PrinterState>,
+    mut confirmed: impl FnMut(&PrinterState) -> bool,
+    timeout: Option<Duration>,
+) -> Result<(), MqttError> {
+    let wait = async {
+        loop {
+            state.changed().await.map_err(|_| MqttError::Disconnected)?;
+            if confirmed(&state.borrow_and_update()) {
+                return Ok(());
+            }
+        }
+    };
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, wait)
+            .await
+            .map_err(|_| MqttError::Timeout)?,
+        None => wait.await,
+    }
+}
+
+fn validate_feedrate(feedrate: Option<u32>) -> Result<(), MqttError> {
+    match feedrate {
+        Some(feedrate) if feedrate == 0 || feedrate > MAX_FEEDRATE => {
+            Err(MqttError::InvalidArgument(format_smolstr!(
+                "feedrate {feedrate} mm/min is not between 1 and {MAX_FEEDRATE}"
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Resolves once the stop flag is set.
+async fn wait_for_stop(stop_flag: &Mutex<bool>) {
+    let mut interval = tokio::time::interval(Duration::from_millis(500));
+    loop {
+        interval.tick().await;
+        if *stop_flag.lock().await {
+            break;
+        }
+    }
+}
+
+/// Fail all inflight commands with [`MqttError::Disconnected`].
+fn fail_inflight_commands(inflight_commands: &std::sync::Mutex<InflightCommands>) {
+    for (_, inflight_command) in inflight_commands.lock().unwrap().drain() {
+        let _ = inflight_command.send(Err(MqttError::Disconnected));
+    }
+}
+
+/// Removes an inflight command once its caller stops waiting for the response, whether it got
+/// one, timed out or was cancelled.
+struct InflightGuard {
+    inflight_commands: Arc<std::sync::Mutex<InflightCommands>>,
+    sequence_id: SmolStr,
+}
+
+impl Drop for InflightGuard {
+    fn drop(&mut self) {
+        self.inflight_commands
+            .lock()
+            .unwrap()
+            .remove(&self.sequence_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+    use tokio_stream::StreamExt;
+
+    use super::{
+        command::{
+            calibration::{CalibrationOptions, PressureAdvance},
+            gcode::{Axis, Fan, Positioning},
+            print::{ProjectFile, SpeedLevel},
+            system::NozzleType,
+            xcam::{AiMonitoringConfig, Sensitivity},
+        },
+        event::HmsEvent,
+        message::{ams::TrayLocation, hms::Hms, print::GcodeState},
+        reconnect::ReconnectPolicy,
+        testing::FakeBroker,
+        MqttClient, MqttError,
+    };
+
+    #[test]
+    fn builder_clamps_keep_alive_and_event_capacity() {
+        // Both would panic in rumqttc and tokio if passed through as is.
+        let client = MqttClient::builder("localhost", "12345678", "01P00A000000000")
+            .keep_alive(Duration::from_millis(500))
+            .event_capacity(0)
+            .build();
+        assert_eq!(client.keep_alive, Duration::from_secs(1));
+        let _events = client.subscribe();
+    }
+
+    #[tokio::test]
+    async fn command_resolves_with_response() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        let (info, ()) = tokio::join!(client.get_version(), async {
+            let request = connection.next_request().await;
+            assert_eq!(request["info"]["command"], "get_version");
+            let sequence_id = request["info"]["sequence_id"].clone();
+            connection
+                .publish(json!({"info": {
+                    "command": "get_version",
+                    "sequence_id": sequence_id,
+                    "module": [],
+                    "result": "success",
+                    "reason": ""
+                }}))
+                .await;
+        });
+        assert_eq!(info.unwrap().result, "success");
+        assert!(client.inflight_commands.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn command_times_out() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        client.set_default_timeout(Some(Duration::from_millis(100)));
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        let (result, _) = tokio::join!(client.get_version(), connection.next_request());
+        assert!(matches!(result, Err(MqttError::Timeout)), "{result:?}");
+        assert!(client.inflight_commands.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn dropped_command_is_forgotten() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        client.set_default_timeout(None);
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        let (result, _) = tokio::join!(
+            tokio::time::timeout(Duration::from_millis(100), client.get_version()),
+            connection.next_request()
+        );
+        assert!(result.is_err());
+        assert!(client.inflight_commands.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn disconnect_fails_inflight_commands() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        client.set_reconnect_policy(ReconnectPolicy::never());
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        let (result, ()) = tokio::join!(client.get_version(), async {
+            connection.next_request().await;
+            drop(connection);
+        });
+        assert!(matches!(result, Err(MqttError::Disconnected)), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn unexpected_response_is_an_error() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        let (access_code, ()) = tokio::join!(client.get_access_code(), async {
+            let request = connection.next_request().await;
+            assert_eq!(request["system"]["command"], "get_access_code");
+            let sequence_id = request["system"]["sequence_id"].clone();
+            connection
+                .publish(json!({"system": {
+                    "command": "get_access_code",
+                    "sequence_id": sequence_id,
+                    "access_code": "12345678",
+                    "reason": "success",
+                    "result": "success"
+                }}))
+                .await;
+        });
+        assert_eq!(access_code.unwrap(), "12345678");
+
+        let (result, ()) = tokio::join!(client.get_version(), async {
+            let request = connection.next_request().await;
+            let sequence_id = request["info"]["sequence_id"].clone();
+            connection
+                .publish(json!({"system": {
+                    "command": "get_version",
+                    "sequence_id": sequence_id,
+                    "reason": "",
+                    "result": "fail"
+                }}))
+                .await;
+        });
+        assert!(
+            matches!(result, Err(MqttError::UnexpectedResponse(_))),
+            "{result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn set_nozzle_sends_canonical_diameter() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        let result = client.set_nozzle(0.5, NozzleType::HardenedSteel).await;
+        assert!(
+            matches!(result, Err(MqttError::InvalidArgument(_))),
+            "{result:?}"
+        );
+
+        let (result, ()) = tokio::join!(
+            client.set_nozzle(0.1 * 4.0, NozzleType::HardenedSteel),
+            async {
+                let request = connection.next_request().await;
+                assert_eq!(request["system"]["command"], "set_accessories");
+                assert_eq!(request["system"]["nozzle_diameter"], 0.4);
+                connection
+                    .publish(json!({"system": {
+                        "sequence_id": request["system"]["sequence_id"],
+                        "command": "set_accessories",
+                        "result": "success"
+                    }}))
+                    .await;
+            }
+        );
+        result.unwrap();
+    }
+
+    #[tokio::test]
+    async fn refresh_state_waits_for_full_report() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        let (state, ()) = tokio::join!(client.refresh_state(), async {
+            let request = connection.next_request().await;
+            assert_eq!(request["pushing"]["command"], "pushall");
+            // A delta sent before the full report does not count.
+            for (msg, gcode_state) in [(1, "IDLE"), (0, "FINISH")] {
+                connection
+                    .publish(json!({"print": {
+                        "command": "push_status",
+                        "sequence_id": "0",
+                        "msg": msg,
+                        "gcode_state": gcode_state
+                    }}))
+                    .await;
+            }
+        });
+        let state = state.unwrap();
+        assert!(state.last_full_report().is_some());
+        assert_eq!(state.status().gcode_state, Some(GcodeState::Finish));
+    }
+
+    #[tokio::test]
+    async fn start_print_sends_project_file() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        let invalid = ProjectFile::new("file:///sdcard/notes.txt");
+        let result = client.start_print(invalid).await;
+        assert!(
+            matches!(result, Err(MqttError::InvalidArgument(_))),
+            "{result:?}"
+        );
+
+        let project = ProjectFile::new("file:///sdcard/benchy.gcode.3mf");
+        let (response, ()) = tokio::join!(client.start_print(project), async {
+            let request = connection.next_request().await;
+            assert_eq!(request["print"]["command"], "project_file");
+            assert_eq!(request["print"]["url"], "file:///sdcard/benchy.gcode.3mf");
+            let sequence_id = request["print"]["sequence_id"].clone();
+            connection
+                .publish(json!({"print": {
+                    "command": "project_file",
+                    "sequence_id": sequence_id,
+                    "param": "Metadata/plate_1.gcode",
+                    "result": "success"
+                }}))
+                .await;
+        });
+        assert_eq!(response.unwrap().command, "project_file");
+    }
+
+    #[tokio::test]
+    async fn pressure_advance_profiles() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        let (profiles, ()) = tokio::join!(client.pressure_advance_profiles("", "0.4"), async {
+            let request = connection.next_request().await;
+            assert_eq!(request["print"]["command"], "extrusion_cali_get");
+            let sequence_id = request["print"]["sequence_id"].clone();
+            connection
+                .publish(json!({"print": {
+                    "command": "extrusion_cali_get",
+                    "sequence_id": sequence_id,
+                    "nozzle_diameter": "0.4",
+                    "filaments": [{
+                        "cali_idx": 3,
+                        "filament_id": "GFA00",
+                        "setting_id": "GFSA00",
+                        "name": "Bambu PLA Basic",
+                        "k_value": "0.020000",
+                        "n_coef": "1.399999"
+                    }],
+                    "reason": "success",
+                    "result": "success"
+                }}))
+                .await;
+        });
+        let profiles = profiles.unwrap();
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].cali_idx, 3);
+        assert_eq!(profiles[0].k_value, 0.02);
+
+        let result = client
+            .delete_pressure_advance(&PressureAdvance::new("GFA00", "new", 0.03), "0.4")
+            .await;
+        assert!(
+            matches!(result, Err(MqttError::InvalidArgument(_))),
+            "{result:?}"
+        );
+        // The P1P has no Micro Lidar.
+        let options = CalibrationOptions {
+            lidar: true,
+            ..Default::default()
+        };
+        let result = client.start_calibration(options).await;
+        assert!(
+            matches!(result, Err(MqttError::Unsupported { .. })),
+            "{result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn set_print_speed_waits_for_report() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        let (result, ()) = tokio::join!(client.set_print_speed(SpeedLevel::Ludicrous), async {
+            let request = connection.next_request().await;
+            assert_eq!(request["print"]["command"], "print_speed");
+            assert_eq!(request["print"]["param"], "4");
+            let report = |spd_lvl, spd_mag| {
+                json!({"print": {
+                    "command": "push_status",
+                    "sequence_id": "100",
+                    "spd_lvl": spd_lvl,
+                    "spd_mag": spd_mag
+                }})
+            };
+            connection.publish(report(2, 100)).await;
+            connection.publish(report(4, 166)).await;
+        });
+        result.unwrap();
+        assert_eq!(
+            client.state().borrow().status().spd_lvl,
+            Some(SpeedLevel::Ludicrous)
+        );
+    }
+
+    #[tokio::test]
+    async fn hms_events_report_raised_and_cleared_errors() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        let mut events = client.hms_events();
+        let report = |hms| {
+            json!({"print": {
+                "command": "push_status",
+                "sequence_id": "200",
+                "hms": hms
+            }})
+        };
+        let runout = Hms {
+            attr: 0x0700_2000,
+            code: 0x0002_0001,
+        };
+        let lidar = Hms {
+            attr: 0x0C00_0100,
+            code: 0x0001_0001,
+        };
+
+        connection
+            .publish(report(json!([{"attr": runout.attr, "code": runout.code}])))
+            .await;
+        assert_eq!(events.next().await, Some(HmsEvent::Raised(runout)));
+
+        connection
+            .publish(report(json!([{"attr": lidar.attr, "code": lidar.code}])))
+            .await;
+        assert_eq!(events.next().await, Some(HmsEvent::Cleared(runout)));
+        assert_eq!(events.next().await, Some(HmsEvent::Raised(lidar)));
+
+        // Errors raised together are ordered by code, not by their order in the report.
+        connection.publish(report(json!([]))).await;
+        assert_eq!(events.next().await, Some(HmsEvent::Cleared(lidar)));
+        connection
+            .publish(report(json!([
+                {"attr": lidar.attr, "code": lidar.code},
+                {"attr": runout.attr, "code": runout.code}
+            ])))
+            .await;
+        assert_eq!(events.next().await, Some(HmsEvent::Raised(runout)));
+        assert_eq!(events.next().await, Some(HmsEvent::Raised(lidar)));
+    }
+
+    #[tokio::test]
+    async fn set_temperature_waits_for_target() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        // The fake broker serial is a P1P, whose bed goes up to 100 degrees and which has no
+        // chamber heater.
+        let result = client.set_bed_temperature(110, None).await;
+        assert!(
+            matches!(result, Err(MqttError::InvalidArgument(_))),
+            "{result:?}"
+        );
+        let result = client.set_chamber_temperature(40, None).await;
+        assert!(
+            matches!(result, Err(MqttError::Unsupported { .. })),
+            "{result:?}"
+        );
+        // Turning a heater off never reaches 0 degrees.
+        let result = client.set_bed_temperature(0, Some(2.0)).await;
+        assert!(
+            matches!(result, Err(MqttError::InvalidArgument(_))),
+            "{result:?}"
+        );
+
+        let (result, ()) = tokio::join!(client.set_nozzle_temperature(220, Some(2.0)), async {
+            let request = connection.next_request().await;
+            assert_eq!(request["print"]["command"], "gcode_line");
+            assert_eq!(request["print"]["param"], "M104 S220\n");
+            for temperature in [150.0, 205.0, 218.5] {
+                connection
+                    .publish(json!({"print": {
+                        "command": "push_status",
+                        "sequence_id": "300",
+                        "nozzle_temper": temperature
+                    }}))
+                    .await;
+            }
+        });
+        result.unwrap();
+        assert_eq!(client.state().borrow().status().nozzle_temper, Some(218.5));
+
+        // A heater that never gets there runs into the default timeout.
+        client.set_default_timeout(Some(Duration::from_millis(100)));
+        let (result, ()) = tokio::join!(client.set_bed_temperature(60, Some(2.0)), async {
+            let request = connection.next_request().await;
+            assert_eq!(request["print"]["param"], "M140 S60\n");
+        });
+        assert!(matches!(result, Err(MqttError::Timeout)), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn set_fan_speed_in_percent() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        // The P1P has no chamber fan.
+        let result = client.set_fan_speed(Fan::Chamber, 50).await;
+        assert!(
+            matches!(result, Err(MqttError::Unsupported { .. })),
+            "{result:?}"
+        );
+        let result = client.set_fan_speed(Fan::Part, 101).await;
+        assert!(
+            matches!(result, Err(MqttError::InvalidArgument(_))),
+            "{result:?}"
+        );
+
+        client.set_fan_speed(Fan::Part, 50).await.unwrap();
+        let request = connection.next_request().await;
+        assert_eq!(request["print"]["command"], "gcode_line");
+        assert_eq!(request["print"]["param"], "M106 P1 S128\n");
+    }
+
+    #[tokio::test]
+    async fn jog_checks_limits_and_state() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        let result = client
+            .jog(Positioning::Absolute, &[(Axis::X, 300.0)], None)
+            .await;
+        assert!(
+            matches!(result, Err(MqttError::InvalidArgument(_))),
+            "{result:?}"
+        );
+        let result = client.jog(Positioning::Relative, &[], None).await;
+        assert!(
+            matches!(result, Err(MqttError::InvalidArgument(_))),
+            "{result:?}"
+        );
+        let result = client.extrude(500.0, None).await;
+        assert!(
+            matches!(result, Err(MqttError::InvalidArgument(_))),
+            "{result:?}"
+        );
+
+        client
+            .jog(Positioning::Relative, &[(Axis::Z, -5.0)], Some(600))
+            .await
+            .unwrap();
+        let request = connection.next_request().await;
+        assert_eq!(request["print"]["command"], "gcode_line");
+        assert_eq!(request["print"]["param"], "G91\nG1 F600\nG0 Z-5\nG90\n");
+
+        client.extrude(10.0, Some(300)).await.unwrap();
+        let request = connection.next_request().await;
+        assert_eq!(request["print"]["param"], "M83\nG1 F300\nG1 E10\nM82\n");
+
+        let mut state = client.state();
+        connection
+            .publish(json!({"print": {
+                "command": "push_status",
+                "sequence_id": "301",
+                "gcode_state": "RUNNING"
+            }}))
+            .await;
+        state.changed().await.unwrap();
+        let result = client.home(&[]).await;
+        assert!(matches!(result, Err(MqttError::Busy(_))), "{result:?}");
+
+        connection
+            .publish(json!({"print": {
+                "command": "push_status",
+                "sequence_id": "302",
+                "gcode_state": "PAUSE"
+            }}))
+            .await;
+        state.changed().await.unwrap();
+        let result = client.extrude(10.0, None).await;
+        assert!(
+            matches!(result, Err(MqttError::Busy(GcodeState::Pause))),
+            "{result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn set_timelapse_waits_for_report() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        let (result, ()) = tokio::join!(client.set_timelapse(true), async {
+            let request = connection.next_request().await;
+            assert_eq!(request["camera"]["command"], "ipcam_timelapse");
+            assert_eq!(request["camera"]["control"], "enable");
+            connection
+                .publish(json!({"print": {
+                    "command": "push_status",
+                    "sequence_id": "302",
+                    "ipcam": {"timelapse": "enable"}
+                }}))
+                .await;
+        });
+        result.unwrap();
+    }
+
+    #[tokio::test]
+    async fn skip_objects_in_running_job() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        let result = client.skip_object_ids(&[145]).await;
+        assert!(
+            matches!(result, Err(MqttError::InvalidArgument(_))),
+            "{result:?}"
+        );
+
+        let mut state = client.state();
+        connection
+            .publish(json!({"print": {
+                "command": "push_status",
+                "sequence_id": "303",
+                "gcode_state": "RUNNING",
+                "s_obj": [397]
+            }}))
+            .await;
+        state.changed().await.unwrap();
+        let result = client.skip_object_ids(&[145, 397]).await;
+        assert!(
+            matches!(result, Err(MqttError::InvalidArgument(ref reason)) if reason.contains("397")),
+            "{result:?}"
+        );
+        let result = client.skip_object_ids(&[]).await;
+        assert!(
+            matches!(result, Err(MqttError::InvalidArgument(_))),
+            "{result:?}"
+        );
+
+        let (result, ()) = tokio::join!(client.skip_object_ids(&[145]), async {
+            let request = connection.next_request().await;
+            assert_eq!(request["print"]["command"], "skip_objects");
+            assert_eq!(request["print"]["obj_list"], json!([145]));
+            connection
+                .publish(json!({"print": {
+                    "command": "push_status",
+                    "sequence_id": "304",
+                    "s_obj": [397, 145]
+                }}))
+                .await;
+        });
+        result.unwrap();
+    }
+
+    #[tokio::test]
+    async fn configure_ai_monitoring_waits_for_xcam() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        let (started, mut connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+
+        // The P1P has no Micro Lidar.
+        let config = AiMonitoringConfig {
+            spaghetti: Some(Sensitivity::Medium),
+            first_layer_inspection: true,
+            buildplate_marker: true,
+            nozzle_clumping: false,
+        };
+        let result = client.configure_ai_monitoring(&config).await;
+        assert!(
+            matches!(result, Err(MqttError::Unsupported { .. })),
+            "{result:?}"
+        );
+
+        let config = AiMonitoringConfig {
+            first_layer_inspection: false,
+            ..config
+        };
+        let (result, ()) = tokio::join!(client.configure_ai_monitoring(&config), async {
+            let mut modules = Vec::new();
+            for _ in 0..3 {
+                let request = connection.next_request().await;
+                assert_eq!(request["xcam"]["command"], "xcam_control_set");
+                modules.push(request["xcam"]["module_name"].clone());
+            }
+            assert_eq!(
+                modules,
+                [
+                    "printing_monitor",
+                    "buildplate_marker_detector",
+                    "clump_detector"
+                ]
+            );
+            // The first report still has the previous settings, and the clump detector is not
+            // reported by this firmware.
+            for printing_monitor in [false, true] {
+                connection
+                    .publish(json!({"print": {
+                        "command": "push_status",
+                        "sequence_id": "305",
+                        "xcam": {
+                            "printing_monitor": printing_monitor,
+                            "buildplate_marker_detector": true,
+                            "halt_print_sensitivity": "medium"
+                        }
+                    }}))
+                    .await;
+            }
+        });
+        result.unwrap();
+        let state = client.state();
+        let xcam = state.borrow().status().xcam.clone().unwrap();
+        assert_eq!(xcam.printing_monitor, Some(true));
+    }
+
+    #[tokio::test]
+    async fn tray_locations_are_checked() {
+        // An A1, which only connects a single AMS lite.
+        let mut client = MqttClient::new("127.0.0.1", "12345678", "03900A000000000");
+        let result = client
+            .load_tray(TrayLocation::Ams { unit: 1, tray: 0 }, 220)
+            .await;
+        assert!(
+            matches!(result, Err(MqttError::Unsupported { .. })),
+            "{result:?}"
+        );
+        let result = client
+            .load_tray(TrayLocation::Ams { unit: 0, tray: 4 }, 220)
+            .await;
+        assert!(
+            matches!(result, Err(MqttError::InvalidArgument(_))),
+            "{result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn reconnect_requests_full_report() {
+        let broker = FakeBroker::bind().await;
+        let mut client = broker.client();
+        client.set_reconnect_policy(ReconnectPolicy {
+            initial_delay: Duration::from_millis(10),
+            ..Default::default()
+        });
+        let (started, connection) = tokio::join!(client.start(), broker.accept());
+        started.unwrap();
+        let used = client.next_sequence_id().await;
+        drop(connection);
+
+        let mut connection = broker.accept().await;
+        let request = connection.next_request().await;
+        assert_eq!(request["pushing"]["command"], "pushall");
+        // The request does not reuse the sequence id of a command that may be inflight.
+        assert_eq!(used, "0");
+        assert_eq!(request["pushing"]["sequence_id"], "1");
+        assert_eq!(client.next_sequence_id().await, "2");
     }
 }
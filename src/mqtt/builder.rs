@@ -0,0 +1,170 @@
+//! Builder for [`MqttClient`].
+use std::{sync::Arc, time::Duration};
+
+use rumqttc::tokio_rustls::rustls::ClientConfig;
+use tokio::sync::{broadcast, watch, Mutex};
+
+use crate::tls::CertificatePolicy;
+
+use super::{
+    model::PrinterModel, reconnect::ReconnectPolicy, state::PrinterState, MqttClient,
+    DEFAULT_CLIENT_CAPACITY, DEFAULT_COMMAND_TIMEOUT, DEFAULT_EVENT_CAPACITY, DEFAULT_KEEP_ALIVE,
+    DEFAULT_MQTT_ID, DEFAULT_MQTT_PORT, DEFAULT_MQTT_USERNAME, MIN_KEEP_ALIVE,
+};
+
+/// How [`MqttClient`] connects to the MQTT broker.
+#[derive(Debug, Clone, Default)]
+pub enum MqttTransport {
+    /// TLS verifying the printer certificate according to the
+    /// [`certificate_policy`](MqttClientBuilder::certificate_policy), which accepts any
+    /// certificate by default.
+    #[default]
+    Tls,
+    /// TLS with a custom rustls configuration.
+    ///
+    /// NOTE: This is the rustls version used by `rumqttc`, which may differ from the one used by
+    /// the rest of this crate.
+    Rustls(Arc<ClientConfig>),
+    /// Plain TCP, e.g. for a local broker.
+    Tcp,
+}
+
+/// Builder for [`MqttClient`].
+///
+/// ```no_run
+/// # use std::time::Duration;
+/// # use bambu::MqttClient;
+/// let client = MqttClient::builder("192.168.1.135", "12345678", "01S00C000000000")
+///     .client_id("dashboard")
+///     .keep_alive(Duration::from_secs(30))
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct MqttClientBuilder {
+    hostname: String,
+    access_code: String,
+    serial: String,
+    port: u16,
+    client_id: String,
+    username: String,
+    keep_alive: Duration,
+    transport: MqttTransport,
+    certificate_policy: CertificatePolicy,
+    capacity: usize,
+    event_capacity: usize,
+    reconnect_policy: ReconnectPolicy,
+    default_timeout: Option<Duration>,
+}
+
+impl MqttClientBuilder {
+    /// Create a builder with the settings used by [`MqttClient::new`].
+    pub fn new(
+        hostname: impl Into<String>,
+        access_code: impl Into<String>,
+        serial: impl Into<String>,
+    ) -> Self {
+        Self {
+            hostname: hostname.into(),
+            access_code: access_code.into(),
+            serial: serial.into(),
+            port: DEFAULT_MQTT_PORT,
+            client_id: DEFAULT_MQTT_ID.to_string(),
+            username: DEFAULT_MQTT_USERNAME.to_string(),
+            keep_alive: DEFAULT_KEEP_ALIVE,
+            transport: MqttTransport::default(),
+            certificate_policy: CertificatePolicy::default(),
+            capacity: DEFAULT_CLIENT_CAPACITY,
+            event_capacity: DEFAULT_EVENT_CAPACITY,
+            reconnect_policy: ReconnectPolicy::default(),
+            default_timeout: Some(DEFAULT_COMMAND_TIMEOUT),
+        }
+    }
+
+    /// Port of the MQTT broker. Defaults to 8883.
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// MQTT client id. Each client connected to the same printer needs a unique id.
+    pub fn client_id(mut self, client_id: impl Into<String>) -> Self {
+        self.client_id = client_id.into();
+        self
+    }
+
+    /// MQTT username. Defaults to `bblp`.
+    pub fn username(mut self, username: impl Into<String>) -> Self {
+        self.username = username.into();
+        self
+    }
+
+    /// MQTT keep-alive interval. Defaults to 60 seconds. Intervals under 1 second are raised to
+    /// 1 second, the minimum supported by `rumqttc`.
+    pub fn keep_alive(mut self, keep_alive: Duration) -> Self {
+        self.keep_alive = keep_alive.max(MIN_KEEP_ALIVE);
+        self
+    }
+
+    /// How to connect to the MQTT broker. Defaults to [`MqttTransport::Tls`].
+    pub fn transport(mut self, transport: MqttTransport) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// How to verify the printer certificate when using [`MqttTransport::Tls`]. Defaults to
+    /// [`CertificatePolicy::Insecure`], which accepts any certificate.
+    pub fn certificate_policy(mut self, policy: CertificatePolicy) -> Self {
+        self.certificate_policy = policy;
+        self
+    }
+
+    /// Capacity of the channel between the client and its event loop. Defaults to 10.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Number of events buffered for each [`MqttClient::subscribe`] stream. Slower subscribers
+    /// miss the oldest events. Defaults to 64, and is raised to at least 1.
+    pub fn event_capacity(mut self, capacity: usize) -> Self {
+        self.event_capacity = capacity.max(1);
+        self
+    }
+
+    /// See [`MqttClient::set_reconnect_policy`].
+    pub fn reconnect_policy(mut self, policy: ReconnectPolicy) -> Self {
+        self.reconnect_policy = policy;
+        self
+    }
+
+    /// See [`MqttClient::set_default_timeout`].
+    pub fn default_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.default_timeout = timeout;
+        self
+    }
+
+    /// Create the client. Call [`MqttClient::start`] to connect.
+    pub fn build(self) -> MqttClient {
+        MqttClient {
+            hostname: self.hostname,
+            port: self.port,
+            client_id: self.client_id,
+            username: self.username,
+            keep_alive: self.keep_alive,
+            transport: self.transport,
+            certificate_policy: self.certificate_policy,
+            capacity: self.capacity,
+            access_code: self.access_code,
+            serial: self.serial,
+            detected_model: PrinterModel::Unknown,
+            client: None,
+            stop_flag: Arc::new(Mutex::new(false)),
+            inflight_commands: Default::default(),
+            sequence_id: Arc::new(Mutex::new(0)),
+            state: Arc::new(watch::Sender::new(PrinterState::default())),
+            events: broadcast::Sender::new(self.event_capacity),
+            reconnect_policy: self.reconnect_policy,
+            default_timeout: self.default_timeout,
+        }
+    }
+}
@@ -1,14 +1,20 @@
+pub mod calibration;
+pub mod camera;
+pub mod gcode;
 pub mod info;
 pub mod print;
 pub mod pushing;
 pub mod system;
+pub mod xcam;
 
+use camera::CameraPayload;
 use info::InfoPayload;
 use print::PrintPayload;
 use pushing::PushingPayload;
 use serde::{Deserialize, Serialize};
 use smol_str::SmolStr;
 use system::SystemPayload;
+use xcam::XcamPayload;
 
 /// Our top-level `Command` enum uses `#[serde(untagged)]`.
 /// Each variant is a different JSON root key:
@@ -16,6 +22,8 @@ use system::SystemPayload;
 ///    { "print": {...} }
 ///    { "pushing": {...} }
 ///    { "system": {...} }
+///    { "camera": {...} }
+///    { "xcam": {...} }
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Command {
@@ -27,6 +35,10 @@ pub enum Command {
     Pushing { pushing: PushingPayload },
     /// e.g. `{"system": { "sequence_id": "77", "command": "ledctrl", ... }}`
     System { system: SystemPayload },
+    /// e.g. `{"camera": { "sequence_id": "12", "command": "ipcam_timelapse", "control": "enable" }}`
+    Camera { camera: CameraPayload },
+    /// e.g. `{"xcam": { "sequence_id": "5", "command": "xcam_control_set", "module_name": ... }}`
+    Xcam { xcam: XcamPayload },
 }
 
 impl Command {
@@ -37,6 +49,8 @@ impl Command {
             Command::Print { print } => &print.sequence_id,
             Command::Pushing { pushing } => &pushing.sequence_id,
             Command::System { system } => &system.sequence_id,
+            Command::Camera { camera } => &camera.sequence_id,
+            Command::Xcam { xcam } => &xcam.sequence_id,
         }
     }
 }
@@ -44,11 +58,15 @@ impl Command {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mqtt::message::ams::{Rgba, TrayLocation};
+    use calibration::{CalibrationOptions, FlowRateCalibration, PressureAdvance};
+    use camera::{CameraCommand, CameraResolution};
     use info::InfoCommand;
-    use print::PrintCommand;
+    use print::{AmsControl, BedType, PrintCommand, ProjectFile, SpeedLevel, TrayFilament};
     use pushing::PushingCommand;
     use serde_json::json;
-    use system::{AccessoryType, LedCtrl, LedMode, LedNode, SystemCommand};
+    use system::{AccessoryType, LedCtrl, LedMode, LedNode, NozzleType, SystemCommand};
+    use xcam::{AiMonitoringConfig, Sensitivity, XcamCommand};
 
     #[test]
     fn test_get_version() {
@@ -199,14 +217,158 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_set_accessories() {
+        let cmd = Command::System {
+            system: SystemPayload {
+                sequence_id: "10".into(),
+                command: SystemCommand::SetAccessories {
+                    accessory_type: AccessoryType::Nozzle,
+                    nozzle_diameter: 0.6,
+                    nozzle_type: NozzleType::HardenedSteel,
+                },
+            },
+        };
+        let actual = serde_json::to_value(&cmd).unwrap();
+        let expected = json!({
+            "system": {
+                "sequence_id": "10",
+                "command": "set_accessories",
+                "accessory_type": "nozzle",
+                "nozzle_diameter": 0.6,
+                "nozzle_type": "hardened_steel"
+            }
+        });
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_set_work_light_flashing() {
+        let cmd = Command::System {
+            system: SystemPayload {
+                sequence_id: "43".into(),
+                command: SystemCommand::LedCtrl(LedCtrl::flashing(
+                    LedNode::WorkLight,
+                    200,
+                    300,
+                    5,
+                    1000,
+                )),
+            },
+        };
+        let actual = serde_json::to_value(&cmd).unwrap();
+        let expected = json!({
+            "system": {
+                "sequence_id": "43",
+                "command": "ledctrl",
+                "led_node": "work_light",
+                "led_mode": "flashing",
+                "led_on_time": 200,
+                "led_off_time": 300,
+                "loop_times": 5,
+                "interval_time": 1000
+            }
+        });
+        assert_eq!(actual, expected);
+
+        // Responses with unknown nodes are kept as `SystemResponse::Unknown` instead.
+        let led = serde_json::from_value::<LedCtrl>(json!({
+            "command": "ledctrl",
+            "led_node": "heatbed_light",
+            "led_mode": "breathing",
+            "led_on_time": 0,
+            "led_off_time": 0,
+            "loop_times": 0,
+            "interval_time": 0
+        }));
+        assert!(led.is_err());
+    }
+
+    #[test]
+    fn test_camera_commands() {
+        let cmd = Command::Camera {
+            camera: CameraPayload {
+                sequence_id: "12".into(),
+                command: CameraCommand::Timelapse {
+                    control: true.into(),
+                },
+            },
+        };
+        let actual = serde_json::to_value(&cmd).unwrap();
+        let expected = json!({
+            "camera": {
+                "sequence_id": "12",
+                "command": "ipcam_timelapse",
+                "control": "enable"
+            }
+        });
+        assert_eq!(actual, expected);
+
+        let cmd = Command::Camera {
+            camera: CameraPayload {
+                sequence_id: "13".into(),
+                command: CameraCommand::ResolutionSet {
+                    resolution: CameraResolution::P720,
+                },
+            },
+        };
+        let actual = serde_json::to_value(&cmd).unwrap();
+        let expected = json!({
+            "camera": {
+                "sequence_id": "13",
+                "command": "ipcam_resolution_set",
+                "resolution": "720p"
+            }
+        });
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_xcam_control_set() {
+        let config = AiMonitoringConfig {
+            spaghetti: Some(Sensitivity::High),
+            buildplate_marker: true,
+            ..Default::default()
+        };
+        let [spaghetti, first_layer, ..] = config.controls();
+        let cmd = Command::Xcam {
+            xcam: XcamPayload {
+                sequence_id: "5".into(),
+                command: XcamCommand::ControlSet(spaghetti),
+            },
+        };
+        let actual = serde_json::to_value(&cmd).unwrap();
+        let expected = json!({
+            "xcam": {
+                "sequence_id": "5",
+                "command": "xcam_control_set",
+                "module_name": "printing_monitor",
+                "control": true,
+                "enable": true,
+                "print_halt": true,
+                "halt_print_sensitivity": "high"
+            }
+        });
+        assert_eq!(actual, expected);
+
+        let actual = serde_json::to_value(XcamCommand::ControlSet(first_layer)).unwrap();
+        let expected = json!({
+            "command": "xcam_control_set",
+            "module_name": "first_layer_inspector",
+            "control": false,
+            "enable": false,
+            "print_halt": true
+        });
+        assert_eq!(actual, expected);
+    }
+
     #[test]
     fn test_set_speed_profile() {
-        // Original snippet used <PROFILE>. We can do "fast" or "superfast"
         let cmd = Command::Print {
             print: PrintPayload {
                 sequence_id: "8910".into(),
                 command: PrintCommand::PrintSpeed {
-                    param: "fast".into(),
+                    param: SpeedLevel::Sport,
                 },
             },
         };
@@ -215,10 +377,23 @@ mod tests {
             "print": {
                 "sequence_id": "8910",
                 "command": "print_speed",
-                "param": "fast"
+                "param": "3"
             }
         });
         assert_eq!(actual, expected);
+
+        let decoded: Command = serde_json::from_value(expected).unwrap();
+        assert!(matches!(
+            decoded,
+            Command::Print {
+                print: PrintPayload {
+                    command: PrintCommand::PrintSpeed {
+                        param: SpeedLevel::Sport
+                    },
+                    ..
+                }
+            }
+        ));
     }
 
     #[test]
@@ -265,7 +440,242 @@ mod tests {
     }
 
     #[test]
+    fn test_project_file() {
+        let mut project = ProjectFile::new("file:///sdcard/benchy.gcode.3mf");
+        project.plate = 2;
+        project.timelapse = true;
+        project.bed_type = BedType::TexturedPlate;
+        project.use_ams = true;
+        project.ams_mapping = vec![0, -1, 254];
+        let cmd = Command::Print {
+            print: PrintPayload {
+                sequence_id: "31".into(),
+                command: PrintCommand::ProjectFile(Box::new(project)),
+            },
+        };
+        let actual = serde_json::to_value(&cmd).unwrap();
+        let expected = json!({
+            "print": {
+                "sequence_id": "31",
+                "command": "project_file",
+                "param": "Metadata/plate_2.gcode",
+                "url": "file:///sdcard/benchy.gcode.3mf",
+                "file": "benchy.gcode.3mf",
+                "subtask_name": "benchy",
+                "md5": "",
+                "project_id": "0",
+                "profile_id": "0",
+                "task_id": "0",
+                "subtask_id": "0",
+                "timelapse": true,
+                "bed_type": "textured_plate",
+                "bed_levelling": true,
+                "flow_cali": true,
+                "vibration_cali": true,
+                "layer_inspect": false,
+                "use_ams": true,
+                "ams_mapping": [0, -1, 254]
+            }
+        });
+        assert_eq!(actual, expected);
+
+        let decoded: Command = serde_json::from_value(expected).unwrap();
+        assert!(matches!(
+            decoded,
+            Command::Print { print: PrintPayload { command: PrintCommand::ProjectFile(project), .. } }
+                if project.plate == 2
+        ));
+    }
+
+    #[test]
+    fn test_project_file_validation() {
+        let valid = ProjectFile::new("ftp://cube.3mf");
+        assert!(valid.validate().is_ok());
+
+        let mut project = valid.clone();
+        project.plate = 0;
+        assert!(project.validate().is_err());
+
+        let project = ProjectFile::new("http://example.com/cube.3mf");
+        assert!(project.validate().is_err());
+
+        let project = ProjectFile::new("file:///sdcard/cube.gcode");
+        assert!(project.validate().is_err());
+
+        let mut project = valid.clone();
+        project.use_ams = true;
+        assert!(project.validate().is_err());
+        project.ams_mapping = vec![3, 16];
+        assert!(project.validate().is_err());
+        project.ams_mapping = vec![3, 15];
+        assert!(project.validate().is_ok());
+        project.use_ams = false;
+        assert!(project.validate().is_err());
+    }
+
+    #[test]
+    fn test_ams_change_filament() {
+        let actual = Command::Print {
+            print: PrintPayload {
+                sequence_id: "40".into(),
+                command: PrintCommand::change_filament(TrayLocation::Ams { unit: 1, tray: 2 }, 220)
+                    .unwrap(),
+            },
+        };
+        let expected = json!({
+            "print": {
+                "sequence_id": "40",
+                "command": "ams_change_filament",
+                "target": 6,
+                "curr_temp": 220,
+                "tar_temp": 220,
+                "ams_id": 1,
+                "slot_id": 2
+            }
+        });
+        assert_eq!(serde_json::to_value(&actual).unwrap(), expected);
+
+        let actual = Command::Print {
+            print: PrintPayload {
+                sequence_id: "41".into(),
+                command: PrintCommand::change_filament(TrayLocation::External, 250).unwrap(),
+            },
+        };
+        let expected = json!({
+            "print": {
+                "sequence_id": "41",
+                "command": "ams_change_filament",
+                "target": 254,
+                "curr_temp": 250,
+                "tar_temp": 250,
+                "ams_id": 255,
+                "slot_id": 0
+            }
+        });
+        assert_eq!(serde_json::to_value(&actual).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_unload_filament() {
+        let actual = Command::Print {
+            print: PrintPayload {
+                sequence_id: "42".into(),
+                command: PrintCommand::UnloadFilament,
+            },
+        };
+        let expected = json!({
+            "print": {
+                "sequence_id": "42",
+                "command": "unload_filament"
+            }
+        });
+        assert_eq!(serde_json::to_value(&actual).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_skip_objects() {
+        let actual = Command::Print {
+            print: PrintPayload {
+                sequence_id: "42".into(),
+                command: PrintCommand::SkipObjects {
+                    obj_list: vec![145, 397],
+                },
+            },
+        };
+        let expected = json!({
+            "print": {
+                "sequence_id": "42",
+                "command": "skip_objects",
+                "obj_list": [145, 397]
+            }
+        });
+        assert_eq!(serde_json::to_value(&actual).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_ams_control() {
+        let actual = Command::Print {
+            print: PrintPayload {
+                sequence_id: "43".into(),
+                command: PrintCommand::AmsControl {
+                    param: AmsControl::Resume,
+                },
+            },
+        };
+        let expected = json!({
+            "print": {
+                "sequence_id": "43",
+                "command": "ams_control",
+                "param": "resume"
+            }
+        });
+        assert_eq!(serde_json::to_value(&actual).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_ams_filament_setting() {
+        let filament = TrayFilament {
+            tray_info_idx: "GFL99".into(),
+            tray_type: "PLA".into(),
+            color: Rgba {
+                r: 0xF4,
+                g: 0xEE,
+                b: 0x2A,
+                a: 0xFF,
+            },
+            nozzle_temp_min: 190,
+            nozzle_temp_max: 230,
+        };
+        assert!(filament.validate().is_ok());
 
+        let actual = Command::Print {
+            print: PrintPayload {
+                sequence_id: "44".into(),
+                command: PrintCommand::filament_setting(
+                    TrayLocation::Ams { unit: 0, tray: 3 },
+                    &filament,
+                )
+                .unwrap(),
+            },
+        };
+        let expected = json!({
+            "print": {
+                "sequence_id": "44",
+                "command": "ams_filament_setting",
+                "ams_id": 0,
+                "tray_id": 3,
+                "tray_info_idx": "GFL99",
+                "setting_id": "",
+                "tray_color": "F4EE2AFF",
+                "nozzle_temp_min": 190,
+                "nozzle_temp_max": 230,
+                "tray_type": "PLA"
+            }
+        });
+        assert_eq!(serde_json::to_value(&actual).unwrap(), expected);
+
+        let actual = Command::Print {
+            print: PrintPayload {
+                sequence_id: "45".into(),
+                command: PrintCommand::filament_setting(TrayLocation::External, &filament).unwrap(),
+            },
+        };
+        let actual = serde_json::to_value(&actual).unwrap();
+        assert_eq!(actual["print"]["ams_id"], 255);
+        assert_eq!(actual["print"]["tray_id"], 254);
+        assert!(
+            PrintCommand::filament_setting(TrayLocation::Ams { unit: 4, tray: 0 }, &filament)
+                .is_err()
+        );
+
+        let invalid = TrayFilament {
+            nozzle_temp_min: 240,
+            ..filament
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
     fn test_extrusion_cali_get() {
         let expected = json!({"print":{"command":"extrusion_cali_get","filament_id":"","nozzle_diameter":"0","sequence_id":"20005"}});
         let actual = Command::Print {
@@ -279,4 +689,131 @@ mod tests {
         };
         assert_eq!(serde_json::to_value(&actual).unwrap(), expected);
     }
+
+    #[test]
+    fn test_extrusion_cali_set_sel_del() {
+        let mut profile = PressureAdvance::new("GFA00", "PLA 0.4", 0.025);
+        let actual = Command::Print {
+            print: PrintPayload {
+                sequence_id: "1".into(),
+                command: PrintCommand::ExtrusionCalibrationSet {
+                    nozzle_diameter: "0.4".into(),
+                    filaments: vec![profile.clone()],
+                },
+            },
+        };
+        let expected = json!({
+            "print": {
+                "sequence_id": "1",
+                "command": "extrusion_cali_set",
+                "nozzle_diameter": "0.4",
+                "filaments": [{
+                    "cali_idx": -1,
+                    "filament_id": "GFA00",
+                    "name": "PLA 0.4",
+                    "k_value": "0.025000",
+                    "n_coef": "1.400000"
+                }]
+            }
+        });
+        assert_eq!(serde_json::to_value(&actual).unwrap(), expected);
+
+        profile.cali_idx = 2;
+        let actual = Command::Print {
+            print: PrintPayload {
+                sequence_id: "2".into(),
+                command: PrintCommand::ExtrusionCalibrationSelect {
+                    tray_id: TrayLocation::Ams { unit: 0, tray: 1 }.tray_id().unwrap(),
+                    cali_idx: profile.cali_idx,
+                    filament_id: profile.filament_id.clone(),
+                    nozzle_diameter: "0.4".into(),
+                },
+            },
+        };
+        let expected = json!({
+            "print": {
+                "sequence_id": "2",
+                "command": "extrusion_cali_sel",
+                "tray_id": 1,
+                "cali_idx": 2,
+                "filament_id": "GFA00",
+                "nozzle_diameter": "0.4"
+            }
+        });
+        assert_eq!(serde_json::to_value(&actual).unwrap(), expected);
+
+        let actual = Command::Print {
+            print: PrintPayload {
+                sequence_id: "3".into(),
+                command: PrintCommand::ExtrusionCalibrationDelete {
+                    cali_idx: 2,
+                    filament_id: "GFA00".into(),
+                    nozzle_diameter: "0.4".into(),
+                },
+            },
+        };
+        let expected = json!({
+            "print": {
+                "sequence_id": "3",
+                "command": "extrusion_cali_del",
+                "cali_idx": 2,
+                "filament_id": "GFA00",
+                "nozzle_diameter": "0.4"
+            }
+        });
+        assert_eq!(serde_json::to_value(&actual).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_calibration() {
+        let options = CalibrationOptions {
+            bed_leveling: true,
+            vibration: true,
+            ..Default::default()
+        };
+        let actual = Command::Print {
+            print: PrintPayload {
+                sequence_id: "4".into(),
+                command: PrintCommand::Calibration { option: options },
+            },
+        };
+        let expected = json!({
+            "print": {
+                "sequence_id": "4",
+                "command": "calibration",
+                "option": 6
+            }
+        });
+        assert_eq!(serde_json::to_value(&actual).unwrap(), expected);
+        assert_eq!(CalibrationOptions::from(6), options);
+
+        let actual = Command::Print {
+            print: PrintPayload {
+                sequence_id: "5".into(),
+                command: PrintCommand::FlowRateCalibration(FlowRateCalibration {
+                    tray_id: TrayLocation::External.tray_id().unwrap(),
+                    filament_id: "GFL99".into(),
+                    setting_id: "".into(),
+                    nozzle_diameter: "0.4".into(),
+                    nozzle_temp: 220,
+                    bed_temp: 55,
+                    max_volumetric_speed: 12.0,
+                }),
+            },
+        };
+        let expected = json!({
+            "print": {
+                "sequence_id": "5",
+                "command": "flowrate_cali",
+                "tray_id": 254,
+                "filament_id": "GFL99",
+                "setting_id": "",
+                "nozzle_diameter": "0.4",
+                "nozzle_temp": 220,
+                "bed_temp": 55,
+                "max_volumetric_speed": "12.000000"
+            }
+        });
+        assert_eq!(serde_json::to_value(&actual).unwrap(), expected);
+    }
 }
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CameraPayload {
+    pub sequence_id: SmolStr,
+
+    #[serde(flatten)]
+    pub command: CameraCommand,
+}
+
+/// Possible commands for the "camera" JSON root, controlling the built-in camera.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "command")]
+pub enum CameraCommand {
+    /// Record every print to the SD card.
+    #[serde(rename = "ipcam_record_set")]
+    RecordSet { control: Toggle },
+    /// Record a timelapse of every print.
+    #[serde(rename = "ipcam_timelapse")]
+    Timelapse { control: Toggle },
+    #[serde(rename = "ipcam_resolution_set")]
+    ResolutionSet { resolution: CameraResolution },
+}
+
+/// A feature switch, sent and reported as `"enable"` or `"disable"`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "SmolStr", into = "SmolStr")]
+pub enum Toggle {
+    Enable,
+    Disable,
+    /// A value this crate does not know about yet.
+    Unknown(SmolStr),
+}
+
+impl Toggle {
+    /// Whether the feature is enabled, or `None` if the value is unknown.
+    pub fn is_enabled(&self) -> Option<bool> {
+        match self {
+            Toggle::Enable => Some(true),
+            Toggle::Disable => Some(false),
+            Toggle::Unknown(_) => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Toggle::Enable => "enable",
+            Toggle::Disable => "disable",
+            Toggle::Unknown(value) => value,
+        }
+    }
+}
+
+impl From<bool> for Toggle {
+    fn from(enabled: bool) -> Self {
+        if enabled {
+            Toggle::Enable
+        } else {
+            Toggle::Disable
+        }
+    }
+}
+
+impl From<SmolStr> for Toggle {
+    fn from(value: SmolStr) -> Self {
+        match value.as_str() {
+            "enable" => Toggle::Enable,
+            "disable" => Toggle::Disable,
+            _ => Toggle::Unknown(value),
+        }
+    }
+}
+
+impl From<Toggle> for SmolStr {
+    fn from(value: Toggle) -> Self {
+        match value {
+            Toggle::Unknown(value) => value,
+            other => SmolStr::new(other.as_str()),
+        }
+    }
+}
+
+/// Resolution of the built-in camera.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "SmolStr", into = "SmolStr")]
+pub enum CameraResolution {
+    P720,
+    P1080,
+    /// A resolution this crate does not know about yet. P1 and A1 printers, whose resolution
+    /// cannot be changed, report an empty string.
+    Unknown(SmolStr),
+}
+
+impl CameraResolution {
+    pub fn as_str(&self) -> &str {
+        match self {
+            CameraResolution::P720 => "720p",
+            CameraResolution::P1080 => "1080p",
+            CameraResolution::Unknown(resolution) => resolution,
+        }
+    }
+}
+
+impl From<SmolStr> for CameraResolution {
+    fn from(resolution: SmolStr) -> Self {
+        match resolution.as_str() {
+            "720p" => CameraResolution::P720,
+            "1080p" => CameraResolution::P1080,
+            _ => CameraResolution::Unknown(resolution),
+        }
+    }
+}
+
+impl From<CameraResolution> for SmolStr {
+    fn from(resolution: CameraResolution) -> Self {
+        match resolution {
+            CameraResolution::Unknown(resolution) => resolution,
+            other => SmolStr::new(other.as_str()),
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! Typed G-code for the `gcode_line` command.
+use std::fmt::Write;
+
+use smol_str::{format_smolstr, SmolStr};
+
+use crate::mqtt::message::print::Print;
+
+/// A heater whose target temperature can be set.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Heater {
+    Nozzle,
+    Bed,
+    Chamber,
+}
+
+impl Heater {
+    /// The current temperature reported in `status`.
+    pub fn temperature(self, status: &Print) -> Option<f64> {
+        match self {
+            Heater::Nozzle => status.nozzle_temper,
+            Heater::Bed => status.bed_temper,
+            Heater::Chamber => status.chamber_temper,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Heater::Nozzle => "nozzle",
+            Heater::Bed => "bed",
+            Heater::Chamber => "chamber",
+        }
+    }
+}
+
+/// A fan whose speed is reported in `push_status`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Fan {
+    /// The part cooling fan on the toolhead (`cooling_fan_speed`).
+    Part,
+    /// The auxiliary part cooling fan on the side of the chamber (`big_fan1_speed`).
+    Auxiliary,
+    /// The chamber exhaust fan (`big_fan2_speed`).
+    Chamber,
+    /// The hotend heatbreak fan (`heatbreak_fan_speed`). It is controlled by the firmware only.
+    Heatbreak,
+}
+
+impl Fan {
+    /// Index of the fan in `M106 P<index>`, or `None` if it cannot be controlled.
+    pub fn index(self) -> Option<u8> {
+        match self {
+            Fan::Part => Some(1),
+            Fan::Auxiliary => Some(2),
+            Fan::Chamber => Some(3),
+            Fan::Heatbreak => None,
+        }
+    }
+
+    /// The current speed reported in `status`, in percent.
+    pub fn speed(self, status: &Print) -> Option<u8> {
+        let step = match self {
+            Fan::Part => &status.cooling_fan_speed,
+            Fan::Auxiliary => &status.big_fan1_speed,
+            Fan::Chamber => &status.big_fan2_speed,
+            Fan::Heatbreak => &status.heatbreak_fan_speed,
+        };
+        let step: u8 = step.as_deref()?.parse().ok()?;
+        Some(fan_step_to_percent(step))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Fan::Part => "part cooling",
+            Fan::Auxiliary => "auxiliary",
+            Fan::Chamber => "chamber",
+            Fan::Heatbreak => "heatbreak",
+        }
+    }
+}
+
+/// Convert a fan speed reported as a step from 0 to 15 to percent.
+pub fn fan_step_to_percent(step: u8) -> u8 {
+    ((u16::from(step.min(15)) * 100 + 7) / 15) as u8
+}
+
+/// `M106 P<fan> S<speed>`: set the speed of `fan` in percent, converted to the 0-255 scale of
+/// the firmware. Returns `None` for fans that cannot be controlled.
+pub fn set_fan_speed(fan: Fan, percent: u8) -> Option<SmolStr> {
+    let index = fan.index()?;
+    let speed = (u16::from(percent.min(100)) * 255 + 50) / 100;
+    Some(format_smolstr!("M106 P{index} S{speed}\n"))
+}
+
+/// A motion axis of the toolhead or bed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub fn name(self) -> &'static str {
+        match self {
+            Axis::X => "X",
+            Axis::Y => "Y",
+            Axis::Z => "Z",
+        }
+    }
+}
+
+/// How the coordinates of a move are interpreted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Positioning {
+    /// Coordinates are distances from the current position (`G91`).
+    Relative,
+    /// Coordinates are positions in the build volume (`G90`).
+    Absolute,
+}
+
+/// Builds a sequence of G-code lines to send in a single `gcode_line` command.
+///
+/// The builder only formats G-code, limits are checked by the [`MqttClient`] helpers using it.
+///
+/// [`MqttClient`]: crate::MqttClient
+#[derive(Debug, Clone, Default)]
+pub struct GcodeBuilder {
+    gcode: String,
+}
+
+impl GcodeBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `G28`: home `axes`, or all axes if empty.
+    pub fn home(mut self, axes: &[Axis]) -> Self {
+        self.gcode.push_str("G28");
+        for axis in axes {
+            self.gcode.push(' ');
+            self.gcode.push_str(axis.name());
+        }
+        self.gcode.push('\n');
+        self
+    }
+
+    /// `G90` or `G91`: interpret the coordinates of the following moves as `positioning`.
+    pub fn positioning(mut self, positioning: Positioning) -> Self {
+        self.gcode.push_str(match positioning {
+            Positioning::Relative => "G91\n",
+            Positioning::Absolute => "G90\n",
+        });
+        self
+    }
+
+    /// `M83`: interpret the following extrusion lengths as distances.
+    pub fn relative_extrusion(mut self) -> Self {
+        self.gcode.push_str("M83\n");
+        self
+    }
+
+    /// `M82`: interpret the following extrusion lengths as positions, the firmware default.
+    pub fn absolute_extrusion(mut self) -> Self {
+        self.gcode.push_str("M82\n");
+        self
+    }
+
+    /// `G1 F<feedrate>`: set the feedrate of the following moves in mm/min.
+    pub fn feedrate(mut self, feedrate: u32) -> Self {
+        let _ = writeln!(self.gcode, "G1 F{feedrate}");
+        self
+    }
+
+    /// `G0`: travel along `moves` without extruding.
+    pub fn travel(mut self, moves: &[(Axis, f64)]) -> Self {
+        self.gcode.push_str("G0");
+        for (axis, value) in moves {
+            let _ = write!(self.gcode, " {}{}", axis.name(), round(*value));
+        }
+        self.gcode.push('\n');
+        self
+    }
+
+    /// `G1 E<length>`: extrude `length` mm of filament, or retract it if negative.
+    pub fn extrude(mut self, length: f64) -> Self {
+        let _ = writeln!(self.gcode, "G1 E{}", round(length));
+        self
+    }
+
+    pub fn build(self) -> SmolStr {
+        self.gcode.into()
+    }
+}
+
+/// Round to the micrometer to avoid printing floating point noise such as `0.30000000000000004`.
+fn round(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
+
+/// `M104`, `M140` or `M141`: set the target temperature of `heater` without waiting.
+pub fn set_temperature(heater: Heater, target: u16) -> SmolStr {
+    let code = match heater {
+        Heater::Nozzle => "M104",
+        Heater::Bed => "M140",
+        Heater::Chamber => "M141",
+    };
+    format_smolstr!("{code} S{target}\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        fan_step_to_percent, set_fan_speed, set_temperature, Axis, Fan, GcodeBuilder, Heater,
+        Positioning,
+    };
+
+    #[test]
+    fn temperature_gcode() {
+        assert_eq!(set_temperature(Heater::Nozzle, 220), "M104 S220\n");
+        assert_eq!(set_temperature(Heater::Bed, 0), "M140 S0\n");
+        assert_eq!(set_temperature(Heater::Chamber, 45), "M141 S45\n");
+    }
+
+    #[test]
+    fn fan_gcode() {
+        assert_eq!(set_fan_speed(Fan::Part, 100).unwrap(), "M106 P1 S255\n");
+        assert_eq!(set_fan_speed(Fan::Auxiliary, 50).unwrap(), "M106 P2 S128\n");
+        assert_eq!(set_fan_speed(Fan::Chamber, 0).unwrap(), "M106 P3 S0\n");
+        assert_eq!(set_fan_speed(Fan::Heatbreak, 100), None);
+    }
+
+    #[test]
+    fn builder() {
+        assert_eq!(GcodeBuilder::new().home(&[]).build(), "G28\n");
+        assert_eq!(
+            GcodeBuilder::new().home(&[Axis::X, Axis::Y]).build(),
+            "G28 X Y\n"
+        );
+        assert_eq!(
+            GcodeBuilder::new()
+                .positioning(Positioning::Relative)
+                .feedrate(3000)
+                .travel(&[(Axis::X, 10.0), (Axis::Z, 0.1 + 0.2)])
+                .positioning(Positioning::Absolute)
+                .build(),
+            "G91\nG1 F3000\nG0 X10 Z0.3\nG90\n"
+        );
+        assert_eq!(
+            GcodeBuilder::new()
+                .relative_extrusion()
+                .feedrate(300)
+                .extrude(-2.5)
+                .absolute_extrusion()
+                .build(),
+            "M83\nG1 F300\nG1 E-2.5\nM82\n"
+        );
+    }
+
+    #[test]
+    fn fan_steps() {
+        assert_eq!(fan_step_to_percent(0), 0);
+        assert_eq!(fan_step_to_percent(7), 47);
+        assert_eq!(fan_step_to_percent(10), 67);
+        assert_eq!(fan_step_to_percent(15), 100);
+        assert_eq!(fan_step_to_percent(20), 100);
+    }
+}
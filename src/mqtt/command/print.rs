@@ -1,5 +1,11 @@
 use serde::{Deserialize, Serialize};
-use smol_str::SmolStr;
+use smol_str::{format_smolstr, SmolStr};
+
+use super::calibration::{CalibrationOptions, FlowRateCalibration, PressureAdvance};
+use crate::mqtt::{
+    message::ams::{Rgba, TrayLocation, EXTERNAL_TRAY_ID, NO_TRAY_ID},
+    MqttError,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PrintPayload {
@@ -23,7 +29,10 @@ pub enum PrintCommand {
 
     // "print_speed" -> has param
     #[serde(rename = "print_speed")]
-    PrintSpeed { param: SmolStr },
+    PrintSpeed {
+        #[serde(with = "speed_param")]
+        param: SpeedLevel,
+    },
     // "gcode_line" -> has param
     #[serde(rename = "gcode_line")]
     GcodeLine { param: SmolStr },
@@ -33,4 +42,378 @@ pub enum PrintCommand {
         filament_id: SmolStr,
         nozzle_diameter: SmolStr,
     },
+    // "extrusion_cali_set" -> creates or edits pressure advance profiles
+    #[serde(rename = "extrusion_cali_set")]
+    ExtrusionCalibrationSet {
+        nozzle_diameter: SmolStr,
+        filaments: Vec<PressureAdvance>,
+    },
+    // "extrusion_cali_sel" -> selects the pressure advance profile used for a tray
+    #[serde(rename = "extrusion_cali_sel")]
+    ExtrusionCalibrationSelect {
+        tray_id: u8,
+        cali_idx: i32,
+        filament_id: SmolStr,
+        nozzle_diameter: SmolStr,
+    },
+    // "extrusion_cali_del" -> deletes a pressure advance profile
+    #[serde(rename = "extrusion_cali_del")]
+    ExtrusionCalibrationDelete {
+        cali_idx: i32,
+        filament_id: SmolStr,
+        nozzle_diameter: SmolStr,
+    },
+    // "flowrate_cali" -> prints a flow rate calibration pattern
+    #[serde(rename = "flowrate_cali")]
+    FlowRateCalibration(FlowRateCalibration),
+    // "calibration" -> runs the calibrations selected in `option`
+    #[serde(rename = "calibration")]
+    Calibration { option: CalibrationOptions },
+    // "project_file" -> starts a print job
+    #[serde(rename = "project_file")]
+    ProjectFile(Box<ProjectFile>),
+    // "ams_change_filament" -> loads the filament of a tray, see `PrintCommand::change_filament`
+    #[serde(rename = "ams_change_filament")]
+    AmsChangeFilament {
+        target: u8,
+        curr_temp: u16,
+        tar_temp: u16,
+        ams_id: u8,
+        slot_id: u8,
+    },
+    // "unload_filament" -> retracts the loaded filament
+    #[serde(rename = "unload_filament")]
+    UnloadFilament,
+    // "ams_control" -> has param
+    #[serde(rename = "ams_control")]
+    AmsControl { param: AmsControl },
+    // "ams_filament_setting" -> see `PrintCommand::filament_setting`
+    #[serde(rename = "ams_filament_setting")]
+    AmsFilamentSetting {
+        ams_id: u8,
+        tray_id: u8,
+        tray_info_idx: SmolStr,
+        setting_id: SmolStr,
+        tray_color: SmolStr,
+        nozzle_temp_min: u16,
+        nozzle_temp_max: u16,
+        tray_type: SmolStr,
+    },
+    // "skip_objects" -> stops printing the objects with the given ids
+    #[serde(rename = "skip_objects")]
+    SkipObjects { obj_list: Vec<u64> },
+}
+
+impl PrintCommand {
+    /// Request the pressure advance profiles of `filament_id`, or of all filaments if empty, for
+    /// `nozzle_diameter`, e.g. `"0.4"`.
+    pub fn extrusion_calibration_get(
+        filament_id: impl Into<SmolStr>,
+        nozzle_diameter: impl Into<SmolStr>,
+    ) -> Self {
+        PrintCommand::ExtrusionCalibrationGet {
+            filament_id: filament_id.into(),
+            nozzle_diameter: nozzle_diameter.into(),
+        }
+    }
+
+    /// Load the filament of the tray at `location`, heating the nozzle to `temperature`.
+    ///
+    /// Fails with [`MqttError::InvalidArgument`] if `location` does not exist.
+    pub fn change_filament(location: TrayLocation, temperature: u16) -> Result<Self, MqttError> {
+        let target = location.checked_tray_id()?;
+        let (ams_id, slot_id) = match location {
+            TrayLocation::Ams { unit, tray } => (unit, tray),
+            TrayLocation::External => (NO_TRAY_ID, 0),
+        };
+        Ok(PrintCommand::AmsChangeFilament {
+            target,
+            curr_temp: temperature,
+            tar_temp: temperature,
+            ams_id,
+            slot_id,
+        })
+    }
+
+    /// Set the filament of the tray at `location`, e.g. for spools without an RFID tag.
+    ///
+    /// Fails with [`MqttError::InvalidArgument`] if `location` does not exist.
+    pub fn filament_setting(
+        location: TrayLocation,
+        filament: &TrayFilament,
+    ) -> Result<Self, MqttError> {
+        location.checked_tray_id()?;
+        let (ams_id, tray_id) = match location {
+            TrayLocation::Ams { unit, tray } => (unit, tray),
+            TrayLocation::External => (NO_TRAY_ID, EXTERNAL_TRAY_ID),
+        };
+        Ok(PrintCommand::AmsFilamentSetting {
+            ams_id,
+            tray_id,
+            tray_info_idx: filament.tray_info_idx.clone(),
+            setting_id: SmolStr::default(),
+            tray_color: format_smolstr!("{}", filament.color),
+            nozzle_temp_min: filament.nozzle_temp_min,
+            nozzle_temp_max: filament.nozzle_temp_max,
+            tray_type: filament.tray_type.clone(),
+        })
+    }
+}
+
+/// Action of the `ams_control` command.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AmsControl {
+    Resume,
+    Reset,
+    Pause,
+}
+
+/// Filament of a tray, as set by [`PrintCommand::filament_setting`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrayFilament {
+    /// Filament preset id, e.g. `GFA00` for BambuLab PLA Basic or `GFL99` for generic PLA.
+    pub tray_info_idx: SmolStr,
+    /// Filament type, e.g. `PLA`.
+    pub tray_type: SmolStr,
+    pub color: Rgba,
+    /// Nozzle temperature range in Celsius.
+    pub nozzle_temp_min: u16,
+    pub nozzle_temp_max: u16,
+}
+
+impl TrayFilament {
+    pub fn validate(&self) -> Result<(), MqttError> {
+        if self.tray_type.is_empty() {
+            return Err(invalid_argument("tray_type is required"));
+        }
+        if self.nozzle_temp_min > self.nozzle_temp_max {
+            return Err(invalid_argument(
+                "nozzle_temp_min must not exceed nozzle_temp_max",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Print speed profile, sent with `print_speed` and reported in `spd_lvl`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "u8", into = "u8")]
+pub enum SpeedLevel {
+    Silent,
+    Standard,
+    Sport,
+    Ludicrous,
+    /// A level this crate does not know about yet.
+    Unknown(u8),
+}
+
+impl SpeedLevel {
+    /// The level number used by the firmware, from 1 (silent) to 4 (ludicrous).
+    pub fn level(self) -> u8 {
+        self.into()
+    }
+
+    /// Nominal speed in percent of the standard speed, as reported in `spd_mag`.
+    pub fn magnitude(self) -> Option<u16> {
+        match self {
+            SpeedLevel::Silent => Some(50),
+            SpeedLevel::Standard => Some(100),
+            SpeedLevel::Sport => Some(124),
+            SpeedLevel::Ludicrous => Some(166),
+            SpeedLevel::Unknown(_) => None,
+        }
+    }
+}
+
+impl From<u8> for SpeedLevel {
+    fn from(level: u8) -> Self {
+        match level {
+            1 => SpeedLevel::Silent,
+            2 => SpeedLevel::Standard,
+            3 => SpeedLevel::Sport,
+            4 => SpeedLevel::Ludicrous,
+            _ => SpeedLevel::Unknown(level),
+        }
+    }
+}
+
+impl From<SpeedLevel> for u8 {
+    fn from(level: SpeedLevel) -> Self {
+        match level {
+            SpeedLevel::Silent => 1,
+            SpeedLevel::Standard => 2,
+            SpeedLevel::Sport => 3,
+            SpeedLevel::Ludicrous => 4,
+            SpeedLevel::Unknown(level) => level,
+        }
+    }
+}
+
+/// The speed level is sent as a string in `print_speed`, unlike `spd_lvl`.
+mod speed_param {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use smol_str::SmolStr;
+
+    use super::SpeedLevel;
+
+    pub fn serialize<S: Serializer>(level: &SpeedLevel, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&level.level())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SpeedLevel, D::Error> {
+        let param = SmolStr::deserialize(deserializer)?;
+        param
+            .parse::<u8>()
+            .map(SpeedLevel::from)
+            .map_err(|_| D::Error::custom(format_args!("invalid speed level {param:?}")))
+    }
+}
+
+/// Options of the `project_file` command, which starts printing a plate of a sliced `.3mf` file.
+///
+/// ```
+/// # use bambu::command::print::{BedType, ProjectFile};
+/// let mut project = ProjectFile::new("file:///sdcard/benchy.gcode.3mf");
+/// project.plate = 2;
+/// project.bed_type = BedType::TexturedPlate;
+/// project.use_ams = true;
+/// project.ams_mapping = vec![0, 2];
+/// assert!(project.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectFile {
+    /// Index of the plate to print, starting at 1.
+    #[serde(rename = "param", with = "plate_param")]
+    pub plate: u32,
+    /// Location of the file, either `file:///sdcard/...` or `ftp://...`.
+    pub url: SmolStr,
+    pub file: SmolStr,
+    pub subtask_name: SmolStr,
+    pub md5: SmolStr,
+    pub project_id: SmolStr,
+    pub profile_id: SmolStr,
+    pub task_id: SmolStr,
+    pub subtask_id: SmolStr,
+    pub timelapse: bool,
+    pub bed_type: BedType,
+    #[serde(rename = "bed_levelling")]
+    pub bed_leveling: bool,
+    pub flow_cali: bool,
+    pub vibration_cali: bool,
+    pub layer_inspect: bool,
+    pub use_ams: bool,
+    /// AMS tray (`ams_id * 4 + tray_id`) used for each filament of the project, `254` for the
+    /// external spool or `-1` for unused filaments.
+    pub ams_mapping: Vec<i32>,
+}
+
+/// Tray id of the external spool in [`ProjectFile::ams_mapping`].
+pub const EXTERNAL_SPOOL: i32 = 254;
+
+impl ProjectFile {
+    /// Print the first plate of the file at `url` without the AMS, with the same defaults as
+    /// Bambu Studio.
+    pub fn new(url: impl Into<SmolStr>) -> Self {
+        let url = url.into();
+        let file = SmolStr::from(url.rsplit('/').next().unwrap_or_default());
+        let subtask_name = SmolStr::from(
+            file.strip_suffix(".gcode.3mf")
+                .or_else(|| file.strip_suffix(".3mf"))
+                .unwrap_or(&file),
+        );
+        Self {
+            plate: 1,
+            url,
+            file,
+            subtask_name,
+            md5: SmolStr::default(),
+            project_id: "0".into(),
+            profile_id: "0".into(),
+            task_id: "0".into(),
+            subtask_id: "0".into(),
+            timelapse: false,
+            bed_type: BedType::Auto,
+            bed_leveling: true,
+            flow_cali: true,
+            vibration_cali: true,
+            layer_inspect: false,
+            use_ams: false,
+            ams_mapping: Vec::new(),
+        }
+    }
+
+    /// Path of the file on the SD card, relative to its root.
+    pub(crate) fn path(&self) -> Option<&str> {
+        self.url
+            .strip_prefix("file:///sdcard/")
+            .or_else(|| self.url.strip_prefix("ftp://"))
+            .map(|path| path.trim_start_matches('/'))
+    }
+
+    /// Check the options before sending them to the printer, which silently ignores most
+    /// invalid jobs.
+    pub fn validate(&self) -> Result<(), MqttError> {
+        if self.plate == 0 {
+            return Err(invalid_argument("plate index starts at 1"));
+        }
+        let path = self
+            .path()
+            .ok_or_else(|| invalid_argument("url must start with file:///sdcard/ or ftp://"))?;
+        if !path.to_ascii_lowercase().ends_with(".3mf") {
+            return Err(invalid_argument("url must point to a .3mf file"));
+        }
+        if self.use_ams && self.ams_mapping.is_empty() {
+            return Err(invalid_argument(
+                "ams_mapping is required when use_ams is set",
+            ));
+        }
+        if !self.use_ams && !self.ams_mapping.is_empty() {
+            return Err(invalid_argument("ams_mapping requires use_ams"));
+        }
+        if let Some(tray) = self
+            .ams_mapping
+            .iter()
+            .find(|&&tray| !matches!(tray, -1 | 0..=15 | EXTERNAL_SPOOL))
+        {
+            return Err(MqttError::InvalidArgument(format_smolstr!(
+                "invalid AMS tray {tray} in ams_mapping"
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn invalid_argument(message: &str) -> MqttError {
+    MqttError::InvalidArgument(message.into())
+}
+
+/// Build plate type, used to pick the bed temperature and the first layer flow.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BedType {
+    Auto,
+    CoolPlate,
+    EngPlate,
+    HotPlate,
+    TexturedPlate,
+}
+
+/// The plate index is sent as the path of its G-code inside the `.3mf` archive.
+mod plate_param {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use smol_str::SmolStr;
+
+    pub fn serialize<S: Serializer>(plate: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("Metadata/plate_{plate}.gcode"))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+        let param = SmolStr::deserialize(deserializer)?;
+        param
+            .strip_prefix("Metadata/plate_")
+            .and_then(|param| param.strip_suffix(".gcode"))
+            .and_then(|plate| plate.parse().ok())
+            .ok_or_else(|| D::Error::custom(format_args!("invalid plate {param:?}")))
+    }
 }
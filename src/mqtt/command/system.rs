@@ -9,48 +9,159 @@ pub struct SystemPayload {
     pub command: SystemCommand,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(tag = "command", rename = "ledctrl")]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 /// "ledctrl" for turning on/off the chamber light, etc.
+///
+/// Times are in milliseconds and only used by [`LedMode::Flashing`].
 pub struct LedCtrl {
     pub led_node: LedNode,
     pub led_mode: LedMode,
+    /// How long the light stays on in each blink.
     pub led_on_time: u32,
+    /// How long the light stays off in each blink.
     pub led_off_time: u32,
+    /// How many times the light blinks in each loop.
     pub loop_times: u32,
+    /// Pause between loops.
     pub interval_time: u32,
 }
 
+impl LedCtrl {
+    /// Turn `led_node` on or off, or flash it with the default timing of the firmware.
+    pub fn new(led_node: LedNode, led_mode: LedMode) -> Self {
+        Self {
+            led_node,
+            led_mode,
+            led_on_time: 500,
+            led_off_time: 500,
+            loop_times: 0,
+            interval_time: 0,
+        }
+    }
+
+    /// Flash `led_node` `loop_times` times, pausing `interval_time` between each loop.
+    pub fn flashing(
+        led_node: LedNode,
+        led_on_time: u32,
+        led_off_time: u32,
+        loop_times: u32,
+        interval_time: u32,
+    ) -> Self {
+        Self {
+            led_node,
+            led_mode: LedMode::Flashing,
+            led_on_time,
+            led_off_time,
+            loop_times,
+            interval_time,
+        }
+    }
+}
+
 /// Enum for system‐level commands like `ledctrl` and `get_accessories`.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "command")]
 pub enum SystemCommand {
+    #[serde(rename = "ledctrl")]
     LedCtrl(LedCtrl),
     /// "get_accessories"
     #[serde(rename = "get_accessories")]
-    GetAccessories {
+    GetAccessories { accessory_type: AccessoryType },
+    /// "set_accessories", e.g. to set the nozzle after swapping the hotend.
+    #[serde(rename = "set_accessories")]
+    SetAccessories {
         accessory_type: AccessoryType,
+        /// In mm.
+        nozzle_diameter: f64,
+        nozzle_type: NozzleType,
     },
+    /// "get_access_code"
+    #[serde(rename = "get_access_code")]
+    GetAccessCode,
 }
 
 /// Instead of `led_node` being a &str, we define a small enum for valid LED nodes.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Nodes this crate does not know about are kept as
+/// [`Reported::Unknown`](crate::mqtt::message::Reported::Unknown) in reports.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum LedNode {
     ChamberLight,
+    /// The toolhead light used by the Micro Lidar, only on the X1 series.
+    WorkLight,
+}
+
+impl LedNode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LedNode::ChamberLight => "chamber_light",
+            LedNode::WorkLight => "work_light",
+        }
+    }
 }
 
 /// Instead of `led_mode` being a String, we define another enum for valid modes.
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+///
+/// Modes this crate does not know about are kept as
+/// [`Reported::Unknown`](crate::mqtt::message::Reported::Unknown) in reports.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum LedMode {
     On,
     Off,
+    /// Blink following the timing of the [`LedCtrl`] command.
+    Flashing,
 }
 
 /// Same approach for "accessory_type" instead of a static str:
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum AccessoryType {
+    /// Used by `get_accessories` to get all accessories.
     None,
+    Nozzle,
+}
+
+/// Material of the nozzle, set with `set_accessories` and reported in `nozzle_type`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "SmolStr", into = "SmolStr")]
+pub enum NozzleType {
+    /// Required for abrasive filaments such as carbon fiber filled ones.
+    HardenedSteel,
+    StainlessSteel,
+    /// A type this crate does not know about yet.
+    Unknown(SmolStr),
 }
+
+impl NozzleType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            NozzleType::HardenedSteel => "hardened_steel",
+            NozzleType::StainlessSteel => "stainless_steel",
+            NozzleType::Unknown(nozzle_type) => nozzle_type,
+        }
+    }
+}
+
+impl From<SmolStr> for NozzleType {
+    fn from(nozzle_type: SmolStr) -> Self {
+        match nozzle_type.as_str() {
+            "hardened_steel" => NozzleType::HardenedSteel,
+            "stainless_steel" => NozzleType::StainlessSteel,
+            _ => NozzleType::Unknown(nozzle_type),
+        }
+    }
+}
+
+impl From<NozzleType> for SmolStr {
+    fn from(nozzle_type: NozzleType) -> Self {
+        match nozzle_type {
+            NozzleType::Unknown(nozzle_type) => nozzle_type,
+            other => SmolStr::new(other.as_str()),
+        }
+    }
+}
+
+/// Nozzle diameters sold by BambuLab, in mm.
+pub const NOZZLE_DIAMETERS: [f64; 4] = [0.2, 0.4, 0.6, 0.8];
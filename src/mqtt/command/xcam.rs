@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct XcamPayload {
+    pub sequence_id: SmolStr,
+
+    #[serde(flatten)]
+    pub command: XcamCommand,
+}
+
+/// Possible commands for the "xcam" JSON root, configuring AI print monitoring.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "command")]
+pub enum XcamCommand {
+    #[serde(rename = "xcam_control_set")]
+    ControlSet(XcamControl),
+}
+
+/// Enable or disable a single detector.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct XcamControl {
+    pub module_name: XcamModule,
+    pub control: bool,
+    /// Same as `control`, read by older firmware.
+    pub enable: bool,
+    /// Pause the print when the detector triggers.
+    pub print_halt: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub halt_print_sensitivity: Option<Sensitivity>,
+}
+
+impl XcamControl {
+    pub fn new(module_name: XcamModule, enabled: bool) -> Self {
+        Self {
+            module_name,
+            control: enabled,
+            enable: enabled,
+            print_halt: true,
+            halt_print_sensitivity: None,
+        }
+    }
+}
+
+/// A detector of the AI print monitoring.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum XcamModule {
+    /// Spaghetti detection, using the camera.
+    PrintingMonitor,
+    /// First layer inspection, using the Micro Lidar of the X1 series.
+    FirstLayerInspector,
+    /// Check that the build plate is placed correctly before printing.
+    BuildplateMarkerDetector,
+    /// Nozzle clumping detection.
+    ClumpDetector,
+}
+
+/// How eagerly a detector halts the print.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "SmolStr", into = "SmolStr")]
+pub enum Sensitivity {
+    Low,
+    Medium,
+    High,
+    /// A level this crate does not know about yet.
+    Unknown(SmolStr),
+}
+
+impl Sensitivity {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Sensitivity::Low => "low",
+            Sensitivity::Medium => "medium",
+            Sensitivity::High => "high",
+            Sensitivity::Unknown(level) => level,
+        }
+    }
+}
+
+impl From<SmolStr> for Sensitivity {
+    fn from(level: SmolStr) -> Self {
+        match level.as_str() {
+            "low" => Sensitivity::Low,
+            "medium" => Sensitivity::Medium,
+            "high" => Sensitivity::High,
+            _ => Sensitivity::Unknown(level),
+        }
+    }
+}
+
+impl From<Sensitivity> for SmolStr {
+    fn from(level: Sensitivity) -> Self {
+        match level {
+            Sensitivity::Unknown(level) => level,
+            other => SmolStr::new(other.as_str()),
+        }
+    }
+}
+
+/// Configuration of all detectors, see [`MqttClient::configure_ai_monitoring`].
+///
+/// [`MqttClient::configure_ai_monitoring`]: crate::MqttClient::configure_ai_monitoring
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AiMonitoringConfig {
+    /// Sensitivity of spaghetti detection, or `None` to disable it.
+    pub spaghetti: Option<Sensitivity>,
+    /// Only available on the X1 series.
+    pub first_layer_inspection: bool,
+    pub buildplate_marker: bool,
+    pub nozzle_clumping: bool,
+}
+
+impl AiMonitoringConfig {
+    /// One `xcam_control_set` per detector.
+    pub fn controls(&self) -> [XcamControl; 4] {
+        [
+            XcamControl {
+                halt_print_sensitivity: self.spaghetti.clone(),
+                ..XcamControl::new(XcamModule::PrintingMonitor, self.spaghetti.is_some())
+            },
+            XcamControl::new(XcamModule::FirstLayerInspector, self.first_layer_inspection),
+            XcamControl::new(XcamModule::BuildplateMarkerDetector, self.buildplate_marker),
+            XcamControl::new(XcamModule::ClumpDetector, self.nozzle_clumping),
+        ]
+    }
+}
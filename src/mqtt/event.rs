@@ -0,0 +1,50 @@
+//! Events published by the MQTT client.
+use std::{sync::Arc, time::Duration};
+
+use bytes::Bytes;
+use rumqttc::{ClientError, ConnectionError};
+use smol_str::SmolStr;
+
+use super::message::{hms::Hms, print::Print, Message};
+
+/// State of the connection to the printer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Connected and subscribed to the printer reports.
+    Connected,
+    /// The connection was lost or closed.
+    Disconnected,
+    /// Waiting `delay` before reconnection attempt number `attempt`.
+    Reconnecting { attempt: u32, delay: Duration },
+}
+
+/// A change of the active HMS errors, see [`MqttClient::hms_events`](super::MqttClient::hms_events).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HmsEvent {
+    Raised(Hms),
+    Cleared(Hms),
+}
+
+/// An event received by the background task of [`MqttClient`](super::MqttClient).
+#[derive(Debug, Clone)]
+pub enum MqttEvent {
+    /// A `push_status` report.
+    Status(Box<Print>),
+    /// A response to a command. This includes responses to commands sent by other clients.
+    Response(Message),
+    /// A payload that could not be parsed into a [`Message`], or a report that could not be merged
+    /// into the printer state.
+    Unparsed {
+        topic: SmolStr,
+        payload: Bytes,
+        error: Arc<serde_json::Error>,
+    },
+    /// The connection state changed.
+    ConnectionState(ConnectionState),
+    /// The connection failed.
+    Error(Arc<ConnectionError>),
+    /// The background task failed to subscribe to the reports or to send a request.
+    ClientError(Arc<ClientError>),
+    /// The subscriber was too slow and missed this many events.
+    Lagged(u64),
+}
@@ -1,8 +1,14 @@
+pub mod ams;
+pub mod firmware;
+pub mod hms;
 pub mod info;
 pub mod print;
 pub mod system;
 
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
 
 use info::Info;
 use print::Print;
@@ -12,7 +18,7 @@ use system::System;
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub enum Message {
     #[serde(rename = "print")]
-    Print(Print),
+    Print(Box<Print>),
     #[serde(rename = "info")]
     Info(Info),
     #[serde(rename = "system")]
@@ -24,8 +30,8 @@ impl TryFrom<Message> for Print {
 
     fn try_from(message: Message) -> Result<Self, Self::Error> {
         match message {
-            Message::Print(print) => Ok(print),
-            _ => Err(TryFromMessageError(())),
+            Message::Print(print) => Ok(*print),
+            other => Err(TryFromMessageError(Box::new(other))),
         }
     }
 }
@@ -36,7 +42,7 @@ impl TryFrom<Message> for Info {
     fn try_from(message: Message) -> Result<Self, Self::Error> {
         match message {
             Message::Info(info) => Ok(info),
-            _ => Err(TryFromMessageError(())),
+            other => Err(TryFromMessageError(Box::new(other))),
         }
     }
 }
@@ -47,15 +53,67 @@ impl TryFrom<Message> for System {
     fn try_from(message: Message) -> Result<Self, Self::Error> {
         match message {
             Message::System(system) => Ok(system),
-            _ => Err(TryFromMessageError(())),
+            other => Err(TryFromMessageError(Box::new(other))),
+        }
+    }
+}
+
+/// A value reported by the printer, which may be one this crate does not know about yet.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Reported<T> {
+    Known(T),
+    /// The value as reported, e.g. from a newer firmware.
+    Unknown(SmolStr),
+}
+
+impl<T: Copy> Reported<T> {
+    /// The value, or `None` if this crate does not know it.
+    pub fn known(&self) -> Option<T> {
+        match self {
+            Reported::Known(value) => Some(*value),
+            Reported::Unknown(_) => None,
         }
     }
 }
 
+/// A message of another root than expected, e.g. an `info` message where a `print` one was
+/// expected. Holds the message.
 #[derive(Debug)]
-pub struct TryFromMessageError(());
+pub struct TryFromMessageError(Box<Message>);
+
+impl TryFromMessageError {
+    pub(crate) fn new(message: Message) -> Self {
+        Self(Box::new(message))
+    }
+
+    pub fn message(&self) -> &Message {
+        &self.0
+    }
+
+    pub fn into_message(self) -> Message {
+        *self.0
+    }
+}
+
+impl fmt::Display for TryFromMessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unexpected `{}` message", self.0.root())
+    }
+}
+
+impl std::error::Error for TryFromMessageError {}
 
 impl Message {
+    /// The JSON root of the message.
+    pub(crate) fn root(&self) -> &'static str {
+        match self {
+            Message::Print(_) => "print",
+            Message::Info(_) => "info",
+            Message::System(_) => "system",
+        }
+    }
+
     pub(crate) fn sequence_id(&self) -> &str {
         match self {
             Message::Print(print) => &print.sequence_id,
@@ -70,11 +128,16 @@ mod tests {
     use serde_json::json;
 
     use crate::mqtt::{
-        command::system::{LedCtrl, LedMode, LedNode},
-        message::system::System,
+        command::system::{AccessoryType, LedCtrl, LedMode, LedNode, NozzleType},
+        message::{
+            firmware::FirmwareVersion,
+            info::Info,
+            system::{System, SystemResponse},
+        },
+        model::PrinterModel,
     };
 
-    use super::Message;
+    use super::{Message, Reported};
 
     const SERIAL_NUMBER_1: &str = "111111111111111";
     const SERIAL_NUMBER_2: &str = "222222222222222";
@@ -89,20 +152,87 @@ mod tests {
             actual,
             Message::System(System {
                 sequence_id: "1".into(),
-                command: LedCtrl {
+                command: SystemResponse::LedCtrl(LedCtrl {
                     led_node: LedNode::ChamberLight,
                     led_mode: LedMode::Off,
                     led_on_time: 500,
                     led_off_time: 500,
                     loop_times: 0,
                     interval_time: 0,
-                },
-                reason: "success".into(),
-                result: "success".into(),
+                }),
+                reason: Some("success".into()),
+                result: Some("success".into()),
             })
         );
     }
 
+    #[test]
+    fn decode_get_accessories_response() {
+        let response = json!({"system":{"sequence_id":"2","command":"get_accessories","accessory_type":"none","aux_part_fan":false,"nozzle_type":"hardened_steel","nozzle_diameter":0.4,"reason":"success","result":"success"}});
+        let message = serde_json::from_value::<Message>(response.clone()).unwrap();
+        let system = System::try_from(message.clone()).unwrap();
+        let SystemResponse::GetAccessories(accessories) = system.command else {
+            panic!("unexpected response {system:?}");
+        };
+        assert_eq!(
+            accessories.accessory_type,
+            Some(Reported::Known(AccessoryType::None))
+        );
+        assert_eq!(accessories.nozzle_type, Some(NozzleType::HardenedSteel));
+        assert_eq!(accessories.nozzle_diameter, Some(0.4));
+        assert_eq!(accessories.unknown["aux_part_fan"], false);
+        assert_eq!(serde_json::to_value(&message).unwrap(), response);
+
+        // Accessory types this crate does not know about are kept as reported.
+        let response = json!({"system":{"sequence_id":"2","command":"get_accessories","accessory_type":"toolhead"}});
+        let message = serde_json::from_value::<Message>(response.clone()).unwrap();
+        let system = System::try_from(message.clone()).unwrap();
+        let SystemResponse::GetAccessories(accessories) = system.command else {
+            panic!("unexpected response {system:?}");
+        };
+        assert_eq!(
+            accessories.accessory_type,
+            Some(Reported::Unknown("toolhead".into()))
+        );
+        assert_eq!(serde_json::to_value(&message).unwrap(), response);
+    }
+
+    #[test]
+    fn decode_other_system_responses() {
+        let response = json!({"system":{"sequence_id":"3","command":"get_access_code","access_code":"12345678","reason":"success","result":"success"}});
+        let message = serde_json::from_value::<Message>(response.clone()).unwrap();
+        let system = System::try_from(message.clone()).unwrap();
+        let SystemResponse::GetAccessCode(access_code) = &system.command else {
+            panic!("unexpected response {system:?}");
+        };
+        assert_eq!(access_code.access_code, "12345678");
+        assert_eq!(serde_json::to_value(&message).unwrap(), response);
+
+        let response = json!({"system":{"sequence_id":"4","command":"set_remote_mode","enable":true,"reason":"","result":"fail"}});
+        let message = serde_json::from_value::<Message>(response.clone()).unwrap();
+        let system = System::try_from(message.clone()).unwrap();
+        assert_eq!(system.command.command(), "set_remote_mode");
+        assert_eq!(system.result.as_deref(), Some("fail"));
+        let SystemResponse::Unknown(fields) = &system.command else {
+            panic!("unexpected response {system:?}");
+        };
+        assert_eq!(fields["enable"], true);
+        assert_eq!(serde_json::to_value(&message).unwrap(), response);
+
+        // A known command with an unexpected shape, without reason and result.
+        let response = json!({"system":{"sequence_id":"5","command":"get_access_code","access_code":12345678}});
+        let message = serde_json::from_value::<Message>(response.clone()).unwrap();
+        let system = System::try_from(message.clone()).unwrap();
+        assert!(matches!(system.command, SystemResponse::Unknown(_)));
+        assert_eq!(system.command.command(), "get_access_code");
+        assert_eq!(system.result, None);
+        assert_eq!(serde_json::to_value(&message).unwrap(), response);
+
+        let error = Info::try_from(message).unwrap_err();
+        assert_eq!(error.to_string(), "unexpected `system` message");
+        assert_eq!(error.into_message().sequence_id(), "5");
+    }
+
     #[test]
     fn test_get_version_parser() {
         let payload = json!({
@@ -161,6 +291,12 @@ mod tests {
         });
 
         let message = serde_json::from_value::<Message>(payload).unwrap();
-        dbg!(message);
+        let info = Info::try_from(message).unwrap();
+        assert_eq!(info.model(), PrinterModel::A1);
+        assert_eq!(info.firmware_version(), None);
+        assert_eq!(
+            info.module("mc").unwrap().version(),
+            Some(FirmwareVersion::new(0, 0, 29, 76))
+        );
     }
 }
@@ -0,0 +1,426 @@
+//! AMS (Automatic Material System) state, reported in the `ams` and `vt_tray` fields of
+//! `push_status`.
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use smol_str::{format_smolstr, SmolStr};
+
+use crate::mqtt::MqttError;
+
+/// Tray id of the external spool, reported in `vt_tray` and used by `tray_now`.
+pub const EXTERNAL_TRAY_ID: u8 = 254;
+/// Value of `tray_now` when no filament is loaded.
+pub const NO_TRAY_ID: u8 = 255;
+
+/// State of all AMS units.
+///
+/// The firmware reports most values as strings, which are kept as is so that reports round-trip.
+/// Use the accessors to decode them.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Ams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ams: Option<Vec<AmsUnit>>,
+    /// Hexadecimal bit field of the connected units.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ams_exist_bits: Option<SmolStr>,
+    /// Hexadecimal bit field of the trays holding a spool, 4 bits per unit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray_exist_bits: Option<SmolStr>,
+    /// Hexadecimal bit field of the trays holding a BambuLab spool, 4 bits per unit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray_is_bbl_bits: Option<SmolStr>,
+    /// Hexadecimal bit field of the trays whose RFID tag was read, 4 bits per unit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray_read_done_bits: Option<SmolStr>,
+    /// Hexadecimal bit field of the trays whose RFID tag is being read, 4 bits per unit.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray_reading_bits: Option<SmolStr>,
+    /// Tray currently feeding the extruder.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray_now: Option<SmolStr>,
+    /// Tray that fed the extruder before the current one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray_pre: Option<SmolStr>,
+    /// Tray the AMS is switching to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray_tar: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub insert_flag: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub power_on_flag: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    #[serde(flatten)]
+    pub unknown: BTreeMap<SmolStr, Value>,
+}
+
+impl Ams {
+    /// The unit with the given id.
+    pub fn unit(&self, id: u8) -> Option<&AmsUnit> {
+        self.ams
+            .as_deref()?
+            .iter()
+            .find(|unit| unit.id() == Some(id))
+    }
+
+    /// The tray at `location`, if it is in an AMS unit.
+    pub fn tray(&self, location: TrayLocation) -> Option<&AmsTray> {
+        match location {
+            TrayLocation::Ams { unit, tray } => self.unit(unit)?.tray(tray),
+            TrayLocation::External => None,
+        }
+    }
+
+    /// Whether the AMS unit `unit` is connected.
+    pub fn unit_exists(&self, unit: u8) -> Option<bool> {
+        bit(self.ams_exist_bits.as_deref()?, unit)
+    }
+
+    /// Whether tray `tray` of AMS unit `unit` holds a spool.
+    pub fn tray_exists(&self, unit: u8, tray: u8) -> Option<bool> {
+        bit(self.tray_exist_bits.as_deref()?, tray_bit(unit, tray)?)
+    }
+
+    /// Whether tray `tray` of AMS unit `unit` holds a BambuLab spool.
+    pub fn tray_is_bbl(&self, unit: u8, tray: u8) -> Option<bool> {
+        bit(self.tray_is_bbl_bits.as_deref()?, tray_bit(unit, tray)?)
+    }
+
+    /// Whether the RFID tag of tray `tray` of AMS unit `unit` was read.
+    pub fn tray_read_done(&self, unit: u8, tray: u8) -> Option<bool> {
+        bit(self.tray_read_done_bits.as_deref()?, tray_bit(unit, tray)?)
+    }
+
+    /// The tray currently feeding the extruder, or `None` if no filament is loaded.
+    pub fn tray_now(&self) -> Option<TrayLocation> {
+        TrayLocation::from_tray_id(self.tray_now.as_deref()?.parse().ok()?)
+    }
+
+    /// The tray the AMS is switching to, or `None` if it is not switching.
+    pub fn tray_tar(&self) -> Option<TrayLocation> {
+        TrayLocation::from_tray_id(self.tray_tar.as_deref()?.parse().ok()?)
+    }
+}
+
+/// Where a spool is loaded.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TrayLocation {
+    /// Tray `tray` (0 to 3) of AMS unit `unit` (0 to 3).
+    Ams { unit: u8, tray: u8 },
+    /// The external spool holder.
+    External,
+}
+
+impl TrayLocation {
+    /// Decode a global tray id as used by `tray_now` and `ams_mapping`: `unit * 4 + tray` for
+    /// AMS trays and 254 for the external spool. Returns `None` for 255 (no tray).
+    pub fn from_tray_id(id: u8) -> Option<Self> {
+        match id {
+            0..=15 => Some(TrayLocation::Ams {
+                unit: id / 4,
+                tray: id % 4,
+            }),
+            EXTERNAL_TRAY_ID => Some(TrayLocation::External),
+            _ => None,
+        }
+    }
+
+    /// The global tray id, or `None` if `unit` or `tray` is above 3. See
+    /// [`from_tray_id`](Self::from_tray_id).
+    pub fn tray_id(self) -> Option<u8> {
+        match self {
+            TrayLocation::Ams { unit, tray } if unit < 4 => tray_bit(unit, tray),
+            TrayLocation::Ams { .. } => None,
+            TrayLocation::External => Some(EXTERNAL_TRAY_ID),
+        }
+    }
+
+    /// The global tray id, for commands sent to the printer.
+    pub(crate) fn checked_tray_id(self) -> Result<u8, MqttError> {
+        self.tray_id().ok_or_else(|| {
+            MqttError::InvalidArgument(format_smolstr!(
+                "{self:?} does not exist, AMS units and trays are numbered 0 to 3"
+            ))
+        })
+    }
+}
+
+/// A single AMS unit.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AmsUnit {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<SmolStr>,
+    /// Humidity level from 1 to 5.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub humidity: Option<SmolStr>,
+    /// Temperature in Celsius. Only measured by the AMS of the X1 series.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temp: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray: Option<Vec<AmsTray>>,
+    #[serde(flatten)]
+    pub unknown: BTreeMap<SmolStr, Value>,
+}
+
+impl AmsUnit {
+    pub fn id(&self) -> Option<u8> {
+        self.id.as_deref()?.parse().ok()
+    }
+
+    /// Humidity level from 1 to 5.
+    pub fn humidity_level(&self) -> Option<u8> {
+        self.humidity
+            .as_deref()?
+            .parse()
+            .ok()
+            .filter(|level| (1..=5).contains(level))
+    }
+
+    /// Temperature in Celsius.
+    pub fn temperature(&self) -> Option<f64> {
+        self.temp.as_deref()?.parse().ok()
+    }
+
+    /// The tray with the given id (0 to 3).
+    pub fn tray(&self, id: u8) -> Option<&AmsTray> {
+        self.tray
+            .as_deref()?
+            .iter()
+            .find(|tray| tray.id() == Some(id))
+    }
+}
+
+/// A single AMS tray, or the external spool (`vt_tray`).
+///
+/// Empty trays only report their `id`.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AmsTray {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<SmolStr>,
+    /// Filament type, e.g. `PLA`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray_type: Option<SmolStr>,
+    /// Filament color as `RRGGBBAA`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray_color: Option<SmolStr>,
+    /// Colors of multi-color filaments as `RRGGBBAA`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cols: Option<Vec<SmolStr>>,
+    /// Filament preset id, e.g. `GFA00` for BambuLab PLA Basic.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray_info_idx: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray_sub_brands: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray_id_name: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray_uuid: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_uid: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray_weight: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tray_diameter: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nozzle_temp_min: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nozzle_temp_max: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bed_temp: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bed_temp_type: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drying_temp: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub drying_time: Option<SmolStr>,
+    /// Remaining filament in percent, or -1 if unknown.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remain: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xcam_info: Option<SmolStr>,
+    #[serde(flatten)]
+    pub unknown: BTreeMap<SmolStr, Value>,
+}
+
+impl AmsTray {
+    pub fn id(&self) -> Option<u8> {
+        self.id.as_deref()?.parse().ok()
+    }
+
+    /// Whether the tray holds no spool.
+    pub fn is_empty(&self) -> bool {
+        self.tray_type.as_deref().unwrap_or_default().is_empty()
+    }
+
+    /// Filament color.
+    pub fn color(&self) -> Option<Rgba> {
+        self.tray_color.as_deref()?.parse().ok()
+    }
+
+    /// Colors of multi-color filaments. Invalid colors are skipped.
+    pub fn colors(&self) -> Vec<Rgba> {
+        self.cols
+            .iter()
+            .flatten()
+            .filter_map(|color| color.parse().ok())
+            .collect()
+    }
+
+    /// Recommended nozzle temperature range in Celsius.
+    pub fn nozzle_temp_range(&self) -> Option<(u16, u16)> {
+        let min = self.nozzle_temp_min.as_deref()?.parse().ok()?;
+        let max = self.nozzle_temp_max.as_deref()?.parse().ok()?;
+        Some((min, max))
+    }
+
+    /// Remaining filament in percent, if known.
+    pub fn remaining(&self) -> Option<u8> {
+        self.remain.and_then(|remain| u8::try_from(remain).ok())
+    }
+}
+
+/// A color decoded from the `RRGGBBAA` hexadecimal notation used by the firmware.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rgba {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseRgbaError(());
+
+impl fmt::Display for ParseRgbaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("expected a color as RRGGBBAA")
+    }
+}
+
+impl std::error::Error for ParseRgbaError {}
+
+impl FromStr for Rgba {
+    type Err = ParseRgbaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != 8 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(ParseRgbaError(()));
+        }
+        let [r, g, b, a] = u32::from_str_radix(s, 16)
+            .map_err(|_| ParseRgbaError(()))?
+            .to_be_bytes();
+        Ok(Rgba { r, g, b, a })
+    }
+}
+
+impl fmt::Display for Rgba {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02X}{:02X}{:02X}{:02X}",
+            self.r, self.g, self.b, self.a
+        )
+    }
+}
+
+/// Bit `index` of a hexadecimal bit field.
+fn bit(bits: &str, index: u8) -> Option<bool> {
+    let bits = u64::from_str_radix(bits, 16).ok()?;
+    Some(index < 64 && bits & (1 << index) != 0)
+}
+
+fn tray_bit(unit: u8, tray: u8) -> Option<u8> {
+    if tray >= 4 {
+        return None;
+    }
+    unit.checked_mul(4)?.checked_add(tray)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Rgba, TrayLocation};
+    use crate::mqtt::message::{print::Print, Message};
+
+    const X1C_PUSH_STATUS: &str = include_str!("testdata/x1c_push_status.json");
+
+    fn decode(payload: &str) -> Print {
+        let message: Message = serde_json::from_str(payload).unwrap();
+        Print::try_from(message).unwrap()
+    }
+
+    #[test]
+    fn decode_x1c_ams() {
+        let print = decode(X1C_PUSH_STATUS);
+        let ams = print.ams.as_ref().unwrap();
+        assert_eq!(ams.unit_exists(0), Some(true));
+        assert_eq!(ams.unit_exists(1), Some(false));
+        assert_eq!(ams.tray_exists(0, 0), Some(true));
+        assert_eq!(ams.tray_exists(0, 1), Some(false));
+        assert_eq!(ams.tray_exists(0, 2), Some(true));
+        assert_eq!(ams.tray_read_done(0, 2), Some(true));
+        assert_eq!(ams.tray_now(), Some(TrayLocation::Ams { unit: 0, tray: 0 }));
+
+        let unit = ams.unit(0).unwrap();
+        assert_eq!(unit.humidity_level(), Some(4));
+        assert_eq!(unit.temperature(), Some(26.4));
+
+        let tray = unit.tray(0).unwrap();
+        assert_eq!(tray.tray_type.as_deref(), Some("PLA"));
+        assert_eq!(tray.tray_info_idx.as_deref(), Some("GFA00"));
+        assert_eq!(
+            tray.color(),
+            Some(Rgba {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 255
+            })
+        );
+        assert_eq!(tray.nozzle_temp_range(), Some((190, 240)));
+        assert_eq!(tray.remaining(), Some(87));
+        assert!(unit.tray(1).unwrap().is_empty());
+        assert_eq!(unit.tray(2).unwrap().remaining(), None);
+
+        let active = print.active_tray().unwrap();
+        assert_eq!(active.id(), Some(0));
+        assert!(print.vt_tray.as_ref().unwrap().is_empty());
+    }
+
+    #[test]
+    fn tray_locations() {
+        assert_eq!(
+            TrayLocation::from_tray_id(13),
+            Some(TrayLocation::Ams { unit: 3, tray: 1 })
+        );
+        assert_eq!(
+            TrayLocation::from_tray_id(254),
+            Some(TrayLocation::External)
+        );
+        assert_eq!(TrayLocation::from_tray_id(255), None);
+        assert_eq!(TrayLocation::Ams { unit: 2, tray: 3 }.tray_id(), Some(11));
+        assert_eq!(TrayLocation::Ams { unit: 4, tray: 0 }.tray_id(), None);
+        assert_eq!(TrayLocation::Ams { unit: 0, tray: 4 }.tray_id(), None);
+        assert_eq!(TrayLocation::Ams { unit: 64, tray: 0 }.tray_id(), None);
+        assert!(TrayLocation::Ams { unit: 1, tray: 7 }
+            .checked_tray_id()
+            .is_err());
+    }
+
+    #[test]
+    fn parse_color() {
+        let color: Rgba = "F4EE2AFF".parse().unwrap();
+        assert_eq!(
+            color,
+            Rgba {
+                r: 0xF4,
+                g: 0xEE,
+                b: 0x2A,
+                a: 0xFF
+            }
+        );
+        assert_eq!(color.to_string(), "F4EE2AFF");
+        assert!("F4EE2A".parse::<Rgba>().is_err());
+        assert!("+4EE2AFF".parse::<Rgba>().is_err());
+    }
+}
@@ -0,0 +1,167 @@
+//! Firmware versions, reported by `get_version` and in the `upgrade_state` of `push_status`.
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use smol_str::SmolStr;
+
+/// A firmware version such as `01.07.00.00`, ordered from oldest to newest.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FirmwareVersion {
+    pub major: u16,
+    pub minor: u16,
+    pub patch: u16,
+    pub build: u16,
+}
+
+impl FirmwareVersion {
+    pub fn new(major: u16, minor: u16, patch: u16, build: u16) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+            build,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseFirmwareVersionError(());
+
+impl fmt::Display for ParseFirmwareVersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("expected a version like 01.07.00.00")
+    }
+}
+
+impl std::error::Error for ParseFirmwareVersionError {}
+
+impl FromStr for FirmwareVersion {
+    type Err = ParseFirmwareVersionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('.').map(|part| {
+            if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(ParseFirmwareVersionError(()));
+            }
+            part.parse().map_err(|_| ParseFirmwareVersionError(()))
+        });
+        let mut next = || parts.next().unwrap_or(Err(ParseFirmwareVersionError(())));
+        let version = Self::new(next()?, next()?, next()?, next()?);
+        match parts.next() {
+            None => Ok(version),
+            Some(_) => Err(ParseFirmwareVersionError(())),
+        }
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:02}.{:02}.{:02}.{:02}",
+            self.major, self.minor, self.patch, self.build
+        )
+    }
+}
+
+/// Status of a firmware upgrade, reported in `upgrade_state.status`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(from = "SmolStr", into = "SmolStr")]
+pub enum UpgradeStatus {
+    Idle,
+    Downloading,
+    Flashing,
+    UpgradeSuccess,
+    UpgradeFail,
+    /// A status this crate does not know about yet.
+    Unknown(SmolStr),
+}
+
+impl UpgradeStatus {
+    pub fn as_str(&self) -> &str {
+        match self {
+            UpgradeStatus::Idle => "IDLE",
+            UpgradeStatus::Downloading => "DOWNLOADING",
+            UpgradeStatus::Flashing => "FLASHING",
+            UpgradeStatus::UpgradeSuccess => "UPGRADE_SUCCESS",
+            UpgradeStatus::UpgradeFail => "UPGRADE_FAIL",
+            UpgradeStatus::Unknown(status) => status,
+        }
+    }
+
+    /// Whether an upgrade is being downloaded or installed.
+    pub fn in_progress(&self) -> bool {
+        matches!(self, UpgradeStatus::Downloading | UpgradeStatus::Flashing)
+    }
+}
+
+impl From<SmolStr> for UpgradeStatus {
+    fn from(status: SmolStr) -> Self {
+        match status.as_str() {
+            "IDLE" => UpgradeStatus::Idle,
+            "DOWNLOADING" => UpgradeStatus::Downloading,
+            "FLASHING" => UpgradeStatus::Flashing,
+            "UPGRADE_SUCCESS" => UpgradeStatus::UpgradeSuccess,
+            "UPGRADE_FAIL" => UpgradeStatus::UpgradeFail,
+            _ => UpgradeStatus::Unknown(status),
+        }
+    }
+}
+
+impl From<UpgradeStatus> for SmolStr {
+    fn from(status: UpgradeStatus) -> Self {
+        match status {
+            UpgradeStatus::Unknown(status) => status,
+            other => SmolStr::new(other.as_str()),
+        }
+    }
+}
+
+impl fmt::Display for UpgradeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An entry of `upgrade_state.new_ver_list`: a module with a newer firmware available.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NewVersion {
+    /// Module name, e.g. `ota` for the printer firmware or `ams/0` for an AMS unit.
+    pub name: SmolStr,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cur_ver: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_ver: Option<SmolStr>,
+    #[serde(flatten)]
+    pub unknown: BTreeMap<SmolStr, Value>,
+}
+
+impl NewVersion {
+    pub fn current(&self) -> Option<FirmwareVersion> {
+        self.cur_ver.as_ref()?.parse().ok()
+    }
+
+    pub fn latest(&self) -> Option<FirmwareVersion> {
+        self.new_ver.as_ref()?.parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FirmwareVersion;
+
+    #[test]
+    fn parse_versions() {
+        let version: FirmwareVersion = "01.07.00.00".parse().unwrap();
+        assert_eq!(version, FirmwareVersion::new(1, 7, 0, 0));
+        assert_eq!(version.to_string(), "01.07.00.00");
+        assert!("00.00.29.76".parse::<FirmwareVersion>().unwrap() < version);
+        assert!("01.06.01.02".parse::<FirmwareVersion>().unwrap() < version);
+        assert!("01.10.00.00".parse::<FirmwareVersion>().unwrap() > version);
+
+        for invalid in ["", "<sw_ver>", "01.07.00", "01.07.00.00.01", "01.+7.00.00"] {
+            assert!(invalid.parse::<FirmwareVersion>().is_err(), "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! HMS (Health Management System) errors, reported in the `hms` and `print_error` fields of
+//! `push_status`.
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// A single entry of the `hms` array.
+///
+/// Together, `attr` and `code` form the identifier shown by Bambu Studio, e.g.
+/// `HMS_0300_0100_0001_0001`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+pub struct Hms {
+    pub attr: u32,
+    pub code: u32,
+}
+
+impl Hms {
+    /// The part of the printer reporting the error.
+    pub fn module(&self) -> HmsModule {
+        HmsModule::from((self.attr >> 24) as u8)
+    }
+
+    pub fn severity(&self) -> HmsSeverity {
+        HmsSeverity::from((self.code >> 16) as u16)
+    }
+
+    /// The error id within the module, without the severity.
+    pub fn error_id(&self) -> u16 {
+        self.code as u16
+    }
+
+    /// The full 64-bit code, `attr` followed by `code`.
+    pub fn full_code(&self) -> u64 {
+        (u64::from(self.attr) << 32) | u64::from(self.code)
+    }
+
+    /// English description of the error, for a handful of common errors only.
+    ///
+    /// The full HMS code list is published by BambuLab and grows with each firmware release, and
+    /// is not embedded in this crate. Most codes return `None`: link to
+    /// [`wiki_url`](Self::wiki_url) instead.
+    pub fn description(&self) -> Option<&'static str> {
+        let code = self.full_code();
+        HMS_DESCRIPTIONS
+            .iter()
+            .find(|(known, _)| *known == code)
+            .map(|(_, description)| *description)
+    }
+
+    /// Page of the BambuLab wiki describing the error.
+    pub fn wiki_url(&self) -> String {
+        format!(
+            "https://wiki.bambulab.com/en/x1/troubleshooting/hmscode/{}",
+            self.digits()
+        )
+    }
+
+    /// `0300_0100_0001_0001`
+    fn digits(&self) -> String {
+        format!(
+            "{:04X}_{:04X}_{:04X}_{:04X}",
+            self.attr >> 16,
+            self.attr & 0xFFFF,
+            self.code >> 16,
+            self.code & 0xFFFF
+        )
+    }
+}
+
+impl fmt::Display for Hms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HMS_{}", self.digits())
+    }
+}
+
+/// The error reported in `print_error`, e.g. `0300_400C`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PrintError(pub u32);
+
+impl PrintError {
+    /// Decode `print_error`, which is 0 when there is no error.
+    pub fn new(print_error: u64) -> Option<Self> {
+        u32::try_from(print_error)
+            .ok()
+            .filter(|&code| code != 0)
+            .map(PrintError)
+    }
+
+    /// The part of the printer reporting the error.
+    pub fn module(&self) -> HmsModule {
+        HmsModule::from((self.0 >> 24) as u8)
+    }
+
+    /// The error id within the module.
+    pub fn error_id(&self) -> u16 {
+        self.0 as u16
+    }
+
+    /// English description of the error, for a handful of common errors only. Like
+    /// [`Hms::description`], most codes return `None`.
+    pub fn description(&self) -> Option<&'static str> {
+        PRINT_ERROR_DESCRIPTIONS
+            .iter()
+            .find(|(known, _)| *known == self.0)
+            .map(|(_, description)| *description)
+    }
+}
+
+impl fmt::Display for PrintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04X}_{:04X}", self.0 >> 16, self.0 & 0xFFFF)
+    }
+}
+
+/// The part of the printer reporting an error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum HmsModule {
+    MotionController,
+    Mainboard,
+    Ams,
+    Toolhead,
+    Xcam,
+    /// A module this crate does not know about yet.
+    Unknown(u8),
+}
+
+impl From<u8> for HmsModule {
+    fn from(module: u8) -> Self {
+        match module {
+            0x03 => HmsModule::MotionController,
+            0x05 => HmsModule::Mainboard,
+            0x07 => HmsModule::Ams,
+            0x08 => HmsModule::Toolhead,
+            0x0C => HmsModule::Xcam,
+            _ => HmsModule::Unknown(module),
+        }
+    }
+}
+
+/// How serious an HMS error is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HmsSeverity {
+    Fatal,
+    Serious,
+    Common,
+    Info,
+    /// A severity this crate does not know about yet.
+    Unknown(u16),
+}
+
+impl From<u16> for HmsSeverity {
+    fn from(severity: u16) -> Self {
+        match severity {
+            1 => HmsSeverity::Fatal,
+            2 => HmsSeverity::Serious,
+            3 => HmsSeverity::Common,
+            4 => HmsSeverity::Info,
+            _ => HmsSeverity::Unknown(severity),
+        }
+    }
+}
+
+/// Descriptions of a few common HMS errors, keyed by [`Hms::full_code`]. This is a stub, not the
+/// published code list: the complete list is on the BambuLab wiki, see [`Hms::wiki_url`].
+static HMS_DESCRIPTIONS: &[(u64, &str)] = &[
+    (
+        0x0300_0100_0001_0001,
+        "The heatbed temperature is abnormal; the heater may be short-circuited.",
+    ),
+    (
+        0x0300_0300_0001_0001,
+        "The hotend cooling fan speed is too slow or stopped.",
+    ),
+    (
+        0x0300_0400_0002_0001,
+        "The part cooling fan speed is too slow or stopped.",
+    ),
+    (0x0700_2000_0002_0001, "AMS A Slot 1 filament has run out."),
+    (0x0700_2100_0002_0001, "AMS A Slot 2 filament has run out."),
+    (0x0700_2200_0002_0001, "AMS A Slot 3 filament has run out."),
+    (0x0700_2300_0002_0001, "AMS A Slot 4 filament has run out."),
+    (0x0C00_0100_0001_0001, "The Micro Lidar camera is offline."),
+];
+
+/// Descriptions of common `print_error` codes.
+static PRINT_ERROR_DESCRIPTIONS: &[(u32, &str)] = &[(0x0300_400C, "The task was canceled.")];
+
+#[cfg(test)]
+mod tests {
+    use super::{Hms, HmsModule, HmsSeverity, PrintError};
+    use crate::mqtt::message::{print::Print, Message};
+
+    const A1_PUSH_STATUS: &str = include_str!("testdata/a1_push_status.json");
+
+    #[test]
+    fn decode_hms() {
+        let message: Message = serde_json::from_str(A1_PUSH_STATUS).unwrap();
+        let print = Print::try_from(message).unwrap();
+        let hms = print.hms.as_ref().unwrap()[0];
+        assert_eq!(hms.to_string(), "HMS_0701_0000_0002_0001");
+        assert_eq!(hms.module(), HmsModule::Ams);
+        assert_eq!(hms.severity(), HmsSeverity::Serious);
+        assert_eq!(hms.error_id(), 1);
+        assert_eq!(
+            hms.wiki_url(),
+            "https://wiki.bambulab.com/en/x1/troubleshooting/hmscode/0701_0000_0002_0001"
+        );
+
+        let print_error = print.print_error_code().unwrap();
+        assert_eq!(print_error.to_string(), "0701_0001");
+        assert_eq!(print_error.module(), HmsModule::Ams);
+    }
+
+    #[test]
+    fn describe_hms() {
+        let hms = Hms {
+            attr: 0x0300_0100,
+            code: 0x0001_0001,
+        };
+        assert_eq!(hms.to_string(), "HMS_0300_0100_0001_0001");
+        assert_eq!(hms.severity(), HmsSeverity::Fatal);
+        assert!(hms.description().is_some());
+
+        let unknown = Hms {
+            attr: 0x1200_0000,
+            code: 0x0009_0001,
+        };
+        assert_eq!(unknown.module(), HmsModule::Unknown(0x12));
+        assert_eq!(unknown.severity(), HmsSeverity::Unknown(9));
+        assert_eq!(unknown.description(), None);
+
+        assert_eq!(PrintError::new(0), None);
+        assert_eq!(
+            PrintError::new(0x0300_400C).unwrap().description(),
+            Some("The task was canceled.")
+        );
+    }
+}
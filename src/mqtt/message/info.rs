@@ -2,6 +2,12 @@
 use serde::{Deserialize, Serialize};
 use smol_str::SmolStr;
 
+use super::firmware::FirmwareVersion;
+use crate::mqtt::model::PrinterModel;
+
+/// Name of the module holding the printer firmware.
+pub const FIRMWARE_MODULE: &str = "ota";
+
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Module {
     pub name: SmolStr,
@@ -14,6 +20,13 @@ pub struct Module {
     pub ota_ver: Option<SmolStr>,
 }
 
+impl Module {
+    /// The parsed `sw_ver`, or `None` if the printer does not report a version for the module.
+    pub fn version(&self) -> Option<FirmwareVersion> {
+        self.sw_ver.parse().ok()
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Info {
     pub command: SmolStr,
@@ -22,3 +35,25 @@ pub struct Info {
     pub result: SmolStr,
     pub reason: SmolStr,
 }
+
+impl Info {
+    /// The module called `name`, e.g. `ota`, `mc` or `ams/0`.
+    pub fn module(&self, name: &str) -> Option<&Module> {
+        self.module.iter().find(|module| module.name == name)
+    }
+
+    /// The version of the printer firmware.
+    pub fn firmware_version(&self) -> Option<FirmwareVersion> {
+        self.module(FIRMWARE_MODULE)?.version()
+    }
+
+    /// The printer model, identified from the `project_name` of its modules. AMS units report an
+    /// empty project name and are ignored.
+    pub fn model(&self) -> PrinterModel {
+        self.module
+            .iter()
+            .map(|module| PrinterModel::from_project_name(&module.project_name))
+            .find(|model| *model != PrinterModel::Unknown)
+            .unwrap_or(PrinterModel::Unknown)
+    }
+}
@@ -1,13 +1,633 @@
 //! Print message.
+use std::{collections::BTreeMap, fmt};
+
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use smol_str::SmolStr;
 
+use super::{
+    ams::{Ams, AmsTray, TrayLocation},
+    firmware::{FirmwareVersion, NewVersion, UpgradeStatus},
+    hms::{Hms, PrintError},
+    Reported,
+};
+use crate::mqtt::command::{
+    calibration::PressureAdvance,
+    camera::{CameraResolution, Toggle},
+    print::SpeedLevel,
+    system::{LedMode, LedNode, NozzleType},
+    xcam::{Sensitivity, XcamModule},
+};
+
 /// Represents the printer status.
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+///
+/// This is the payload of both `push_status` reports and responses to `print` commands. Every
+/// field other than `command` and `sequence_id` is optional, because P1 and A1 printers only
+/// send the fields that changed since the previous report. Fields that are not modeled yet are
+/// kept in [`Print::unknown`], so serializing a decoded report gives back the original payload.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
 pub struct Print {
-    pub bed_temper: Option<f64>,
-    pub nozzle_temper: Option<f64>,
     pub command: SmolStr,
-    pub msg: u64,
     pub sequence_id: SmolStr,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub msg: Option<u64>,
+    /// Outcome of a `print` command, e.g. `success`. Only set in command responses.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<SmolStr>,
+    /// Why a `print` command failed. Not sent by all firmware versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<SmolStr>,
+
+    // Temperatures (in Celsius).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nozzle_temper: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nozzle_target_temper: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bed_temper: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bed_target_temper: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chamber_temper: Option<f64>,
+
+    // Fans. The firmware reports speeds as strings holding a 0-15 step, see `Fan::speed`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cooling_fan_speed: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heatbreak_fan_speed: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub big_fan1_speed: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub big_fan2_speed: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fan_gear: Option<u64>,
+
+    // Job progress.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gcode_state: Option<GcodeState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gcode_file: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gcode_file_prepare_percent: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gcode_start_time: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mc_percent: Option<u8>,
+    /// Remaining print time in minutes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mc_remaining_time: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mc_print_stage: Option<StringOrNumber>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mc_print_sub_stage: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mc_print_error_code: Option<StringOrNumber>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layer_num: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_layer_num: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stg: Option<Vec<u64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stg_cur: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub print_type: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub print_error: Option<u64>,
+    /// Active HMS errors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hms: Option<Vec<Hms>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fail_reason: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtask_name: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtask_id: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<SmolStr>,
+    /// Ids of the objects skipped in the current job.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub s_obj: Option<Vec<u64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile_id: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub queue_number: Option<u64>,
+
+    // Print speed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spd_lvl: Option<SpeedLevel>,
+    /// Current speed in percent of the standard speed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spd_mag: Option<u16>,
+
+    // Hardware.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub home_flag: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hw_switch_state: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sdcard: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nozzle_diameter: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nozzle_type: Option<NozzleType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wifi_signal: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lifecycle: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub force_upgrade: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub online: Option<Online>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lights_report: Option<Vec<LightReport>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipcam: Option<Ipcam>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub xcam: Option<Xcam>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upgrade_state: Option<UpgradeState>,
+
+    // Filament.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ams: Option<Ams>,
+    /// The external spool.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vt_tray: Option<AmsTray>,
+    /// Pressure advance profiles, in the response to `extrusion_cali_get`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filaments: Option<Vec<PressureAdvance>>,
+
+    /// Fields reported by the printer that are not modeled above.
+    #[serde(flatten)]
+    pub unknown: BTreeMap<SmolStr, Value>,
+}
+
+impl Print {
+    /// Why the printer rejected the command this responds to, or `None` if it succeeded or the
+    /// response has no `result`. Falls back to the `result` if no `reason` is given.
+    pub fn rejection(&self) -> Option<&str> {
+        let result = self.result.as_deref()?;
+        if result.eq_ignore_ascii_case("success") {
+            return None;
+        }
+        match self.reason.as_deref() {
+            Some(reason) if !reason.is_empty() => Some(reason),
+            _ => Some(result),
+        }
+    }
+
+    /// The decoded `print_error`, or `None` if there is no error.
+    pub fn print_error_code(&self) -> Option<PrintError> {
+        PrintError::new(self.print_error?)
+    }
+
+    /// The reported mode of the light `node`, if the printer has it.
+    pub fn light_mode(&self, node: LedNode) -> Option<&Reported<LedMode>> {
+        self.lights_report
+            .as_ref()?
+            .iter()
+            .find(|light| light.node == Reported::Known(node))
+            .map(|light| &light.mode)
+    }
+
+    /// The tray currently feeding the extruder, either in an AMS unit or the external spool.
+    pub fn active_tray(&self) -> Option<&AmsTray> {
+        let ams = self.ams.as_ref()?;
+        match ams.tray_now()? {
+            TrayLocation::External => self.vt_tray.as_ref(),
+            location => ams.tray(location),
+        }
+    }
+}
+
+/// State of the current print job as reported in `gcode_state`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(from = "SmolStr", into = "SmolStr")]
+pub enum GcodeState {
+    Idle,
+    Prepare,
+    Slicing,
+    Running,
+    Pause,
+    Finish,
+    Failed,
+    /// A state this crate does not know about yet.
+    Unknown(SmolStr),
+}
+
+impl GcodeState {
+    pub fn as_str(&self) -> &str {
+        match self {
+            GcodeState::Idle => "IDLE",
+            GcodeState::Prepare => "PREPARE",
+            GcodeState::Slicing => "SLICING",
+            GcodeState::Running => "RUNNING",
+            GcodeState::Pause => "PAUSE",
+            GcodeState::Finish => "FINISH",
+            GcodeState::Failed => "FAILED",
+            GcodeState::Unknown(state) => state,
+        }
+    }
+}
+
+impl From<SmolStr> for GcodeState {
+    fn from(state: SmolStr) -> Self {
+        match state.as_str() {
+            "IDLE" => GcodeState::Idle,
+            "PREPARE" => GcodeState::Prepare,
+            "SLICING" => GcodeState::Slicing,
+            "RUNNING" => GcodeState::Running,
+            "PAUSE" => GcodeState::Pause,
+            "FINISH" => GcodeState::Finish,
+            "FAILED" => GcodeState::Failed,
+            _ => GcodeState::Unknown(state),
+        }
+    }
+}
+
+impl From<GcodeState> for SmolStr {
+    fn from(state: GcodeState) -> Self {
+        match state {
+            GcodeState::Unknown(state) => state,
+            other => SmolStr::new(other.as_str()),
+        }
+    }
+}
+
+impl fmt::Display for GcodeState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A field that some firmware versions report as a string and others as a number.
+///
+/// The original representation is kept, so re-serializing a report gives back the same JSON.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum StringOrNumber {
+    String(SmolStr),
+    Number(serde_json::Number),
+}
+
+impl StringOrNumber {
+    /// The value as an unsigned integer, if it is one in either representation.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            StringOrNumber::String(value) => value.parse().ok(),
+            StringOrNumber::Number(value) => value.as_u64(),
+        }
+    }
+}
+
+impl fmt::Display for StringOrNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StringOrNumber::String(value) => f.write_str(value),
+            StringOrNumber::Number(value) => value.fmt(f),
+        }
+    }
+}
+
+/// Connectivity of optional accessories.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Online {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ahb: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rfid: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    #[serde(flatten)]
+    pub unknown: BTreeMap<SmolStr, Value>,
+}
+
+/// A single entry of the `lights_report` array.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LightReport {
+    pub node: Reported<LedNode>,
+    pub mode: Reported<LedMode>,
+}
+
+/// The built-in camera settings.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Ipcam {
+    /// `"1"` if the printer has a camera.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipcam_dev: Option<SmolStr>,
+    /// Whether prints are recorded to the SD card.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipcam_record: Option<Toggle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timelapse: Option<Toggle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resolution: Option<CameraResolution>,
+    /// Whether the camera can be viewed remotely through the BambuLab cloud.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tutk_server: Option<Toggle>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode_bits: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rtsp_url: Option<SmolStr>,
+    #[serde(flatten)]
+    pub unknown: BTreeMap<SmolStr, Value>,
+}
+
+impl Ipcam {
+    /// Whether the printer has a camera, or `None` if not reported.
+    pub fn has_camera(&self) -> Option<bool> {
+        self.ipcam_dev.as_ref().map(|dev| dev == "1")
+    }
+}
+
+/// The AI print monitoring settings.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Xcam {
+    /// Whether spaghetti detection is enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub printing_monitor: Option<bool>,
+    /// Same as `printing_monitor`, reported by older firmware.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spaghetti_detector: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_layer_inspector: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buildplate_marker_detector: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clump_detector: Option<bool>,
+    /// Whether the print is paused when a detector triggers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub print_halt: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub halt_print_sensitivity: Option<Sensitivity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_skip_parts: Option<bool>,
+    #[serde(flatten)]
+    pub unknown: BTreeMap<SmolStr, Value>,
+}
+
+impl Xcam {
+    /// Whether `module` is enabled, or `None` if not reported.
+    pub fn enabled(&self, module: XcamModule) -> Option<bool> {
+        match module {
+            XcamModule::PrintingMonitor => self.printing_monitor.or(self.spaghetti_detector),
+            XcamModule::FirstLayerInspector => self.first_layer_inspector,
+            XcamModule::BuildplateMarkerDetector => self.buildplate_marker_detector,
+            XcamModule::ClumpDetector => self.clump_detector,
+        }
+    }
+}
+
+/// Firmware upgrade progress.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct UpgradeState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<UpgradeStatus>,
+    /// Percentage of the current upgrade, see [`UpgradeState::progress`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub module: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub err_code: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub force_upgrade: Option<bool>,
+    /// 1 if a new version is available, 2 if not.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_version_state: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ota_new_version_number: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ams_new_version_number: Option<SmolStr>,
+    /// Modules with a newer firmware available.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_ver_list: Option<Vec<NewVersion>>,
+    #[serde(flatten)]
+    pub unknown: BTreeMap<SmolStr, Value>,
+}
+
+impl UpgradeState {
+    /// Percentage of the current upgrade.
+    pub fn progress(&self) -> Option<u8> {
+        self.progress.as_ref()?.parse().ok()
+    }
+
+    /// Whether a newer firmware is available, or `None` if not reported.
+    pub fn new_version_available(&self) -> Option<bool> {
+        match self.new_version_state? {
+            1 => Some(true),
+            2 => Some(false),
+            _ => None,
+        }
+    }
+
+    /// The printer firmware version available for upgrade.
+    pub fn new_firmware_version(&self) -> Option<FirmwareVersion> {
+        self.ota_new_version_number.as_ref()?.parse().ok()
+    }
+
+    /// Whether the printer should be upgraded: a newer firmware is available, or the printer
+    /// requires an upgrade before it can be used.
+    pub fn needs_update(&self) -> bool {
+        self.force_upgrade == Some(true) || self.new_version_available() == Some(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::{GcodeState, LightReport, Print, Reported, SpeedLevel, StringOrNumber};
+    use crate::mqtt::command::{
+        camera::{CameraResolution, Toggle},
+        gcode::Fan,
+        system::{LedMode, LedNode},
+        xcam::{Sensitivity, XcamModule},
+    };
+    use crate::mqtt::message::{
+        firmware::{FirmwareVersion, UpgradeStatus},
+        Message,
+    };
+
+    const X1C_PUSH_STATUS: &str = include_str!("testdata/x1c_push_status.json");
+    const P1S_PUSH_STATUS: &str = include_str!("testdata/p1s_push_status.json");
+    const P1P_PUSH_STATUS_DELTA: &str = include_str!("testdata/p1p_push_status_delta.json");
+    const P1P_LEGACY_PUSH_STATUS: &str = include_str!("testdata/p1p_01.02.00.00_push_status.json");
+    const A1_PUSH_STATUS: &str = include_str!("testdata/a1_push_status.json");
+    const A1_MINI_PUSH_STATUS: &str = include_str!("testdata/a1_mini_push_status.json");
+
+    fn decode(payload: &str) -> Print {
+        let message = serde_json::from_str::<Message>(payload).unwrap();
+        Print::try_from(message).unwrap()
+    }
+
+    fn assert_round_trip(payload: &str) {
+        let expected = serde_json::from_str::<Value>(payload).unwrap();
+        let message = serde_json::from_str::<Message>(payload).unwrap();
+        let actual = serde_json::to_value(&message).unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    // No captures from real printers were available, so these fixtures are written by hand after
+    // the reports of each model and firmware. They only check that the decoder round-trips the
+    // shapes it was written for, and are not regression data. Replace them with captures, with
+    // serials and access codes redacted, when available.
+    #[test]
+    fn round_trip_hand_written_payloads() {
+        assert_round_trip(X1C_PUSH_STATUS);
+        assert_round_trip(P1S_PUSH_STATUS);
+        assert_round_trip(P1P_PUSH_STATUS_DELTA);
+        assert_round_trip(P1P_LEGACY_PUSH_STATUS);
+        assert_round_trip(A1_PUSH_STATUS);
+        assert_round_trip(A1_MINI_PUSH_STATUS);
+    }
+
+    #[test]
+    fn decode_x1c_push_status() {
+        let print = decode(X1C_PUSH_STATUS);
+        assert_eq!(print.command, "push_status");
+        assert_eq!(print.sequence_id, "2021");
+        assert_eq!(print.gcode_state, Some(GcodeState::Running));
+        assert_eq!(print.nozzle_target_temper, Some(220.0));
+        assert_eq!(print.chamber_temper, Some(31.0));
+        assert_eq!(print.mc_percent, Some(37));
+        assert_eq!(print.mc_remaining_time, Some(63));
+        assert_eq!(print.layer_num, Some(42));
+        assert_eq!(print.total_layer_num, Some(240));
+        assert_eq!(print.subtask_name.as_deref(), Some("3D Benchy"));
+        assert_eq!(print.cooling_fan_speed.as_deref(), Some("15"));
+        assert_eq!(Fan::Part.speed(&print), Some(100));
+        assert_eq!(print.spd_lvl, Some(SpeedLevel::Standard));
+        assert_eq!(print.spd_lvl.and_then(SpeedLevel::magnitude), print.spd_mag);
+        assert_eq!(print.lights_report.as_ref().unwrap().len(), 2);
+        let mode = |node| print.light_mode(node).and_then(Reported::known);
+        assert_eq!(mode(LedNode::ChamberLight), Some(LedMode::On));
+        assert_eq!(mode(LedNode::WorkLight), Some(LedMode::Flashing));
+        let ipcam = print.ipcam.as_ref().unwrap();
+        assert_eq!(ipcam.has_camera(), Some(true));
+        assert_eq!(ipcam.ipcam_record, Some(Toggle::Enable));
+        assert_eq!(ipcam.resolution, Some(CameraResolution::P1080));
+        let upgrade_state = print.upgrade_state.as_ref().unwrap();
+        assert_eq!(upgrade_state.status, Some(UpgradeStatus::Idle));
+        assert_eq!(upgrade_state.new_version_available(), Some(false));
+        assert!(!upgrade_state.needs_update());
+        assert_eq!(print.s_obj, Some(vec![]));
+        let xcam = print.xcam.as_ref().unwrap();
+        assert_eq!(xcam.printing_monitor, Some(true));
+        assert_eq!(xcam.first_layer_inspector, Some(true));
+        assert_eq!(xcam.halt_print_sensitivity, Some(Sensitivity::Medium));
+        assert!(xcam.unknown.is_empty());
+    }
+
+    #[test]
+    fn decode_p1s_upgrade_state() {
+        let print = decode(P1S_PUSH_STATUS);
+        let upgrade_state = print.upgrade_state.as_ref().unwrap();
+        assert_eq!(upgrade_state.progress(), Some(0));
+        assert!(upgrade_state.needs_update());
+        assert_eq!(
+            upgrade_state.new_firmware_version(),
+            Some(FirmwareVersion::new(1, 7, 0, 0))
+        );
+        let new_version = &upgrade_state.new_ver_list.as_ref().unwrap()[0];
+        assert_eq!(new_version.name, "ota");
+        assert!(new_version.current() < new_version.latest());
+    }
+
+    #[test]
+    fn decode_p1p_delta() {
+        let print = decode(P1P_PUSH_STATUS_DELTA);
+        assert_eq!(print.gcode_state, Some(GcodeState::Running));
+        assert_eq!(print.mc_percent, Some(64));
+        assert_eq!(print.nozzle_target_temper, None);
+        assert_eq!(print.lights_report, None);
+        assert!(print.unknown.is_empty());
+    }
+
+    #[test]
+    fn decode_legacy_p1p_push_status() {
+        let print = decode(P1P_LEGACY_PUSH_STATUS);
+        assert_eq!(print.msg, Some(0));
+        assert_eq!(print.gcode_state, Some(GcodeState::Finish));
+        assert_eq!(print.mc_print_stage, Some(StringOrNumber::Number(1.into())));
+        assert_eq!(print.mc_print_stage.as_ref().unwrap().as_u64(), Some(1));
+        let xcam = print.xcam.as_ref().unwrap();
+        assert_eq!(xcam.printing_monitor, None);
+        assert_eq!(xcam.enabled(XcamModule::PrintingMonitor), Some(true));
+        let upgrade_state = print.upgrade_state.as_ref().unwrap();
+        assert_eq!(upgrade_state.new_ver_list, None);
+        assert_eq!(upgrade_state.new_firmware_version(), None);
+        assert_eq!(print.ipcam.as_ref().unwrap().resolution, None);
+        assert_eq!(print.s_obj, None);
+    }
+
+    #[test]
+    fn decode_a1_push_status() {
+        let print = decode(A1_PUSH_STATUS);
+        assert_eq!(print.gcode_state, Some(GcodeState::Pause));
+        assert_eq!(print.home_flag, Some(-1065336016));
+        assert_eq!(print.print_error, Some(117506049));
+        assert_eq!(
+            print.ipcam.as_ref().unwrap().unknown["brtc_service"],
+            "enable"
+        );
+
+        let print = decode(A1_MINI_PUSH_STATUS);
+        assert_eq!(print.gcode_state, Some(GcodeState::Finish));
+        assert_eq!(print.stg_cur, Some(-1));
+        assert_eq!(print.sdcard, Some(false));
+    }
+
+    #[test]
+    fn unknown_light_report() {
+        let light: LightReport = serde_json::from_value(
+            serde_json::json!({"node": "heatbed_light", "mode": "breathing"}),
+        )
+        .unwrap();
+        assert_eq!(light.node, Reported::Unknown("heatbed_light".into()));
+        assert_eq!(light.mode.known(), None);
+        let light: LightReport =
+            serde_json::from_value(serde_json::json!({"node": "work_light", "mode": "off"}))
+                .unwrap();
+        assert_eq!(light.node, Reported::Known(LedNode::WorkLight));
+        assert_eq!(light.mode.known(), Some(LedMode::Off));
+    }
+
+    #[test]
+    fn command_result() {
+        let response = |value| serde_json::from_value::<Print>(value).unwrap();
+        let accepted = response(serde_json::json!({
+            "command": "project_file",
+            "sequence_id": "1",
+            "result": "SUCCESS"
+        }));
+        assert_eq!(accepted.rejection(), None);
+        assert!(accepted.unknown.is_empty());
+
+        let rejected = response(serde_json::json!({
+            "command": "project_file",
+            "sequence_id": "2",
+            "result": "FAIL",
+            "reason": "sdcard not found"
+        }));
+        assert_eq!(rejected.rejection(), Some("sdcard not found"));
+        let rejected = Print {
+            reason: Some("".into()),
+            ..rejected
+        };
+        assert_eq!(rejected.rejection(), Some("FAIL"));
+        assert_eq!(Print::default().rejection(), None);
+    }
+
+    #[test]
+    fn unknown_gcode_state() {
+        let state = serde_json::from_str::<GcodeState>("\"CALIBRATING\"").unwrap();
+        assert_eq!(state, GcodeState::Unknown("CALIBRATING".into()));
+        assert_eq!(serde_json::to_string(&state).unwrap(), "\"CALIBRATING\"");
+    }
 }
@@ -1,13 +1,139 @@
-use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
 use smol_str::SmolStr;
 
-use crate::mqtt::command::system::LedCtrl;
+use super::Reported;
+use crate::mqtt::command::system::{AccessoryType, LedCtrl, NozzleType};
 
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct System {
     pub sequence_id: SmolStr,
     #[serde(flatten)]
-    pub command: LedCtrl,
-    pub reason: SmolStr,
-    pub result: SmolStr,
+    pub command: SystemResponse,
+    /// Not sent by all firmware versions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<SmolStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<SmolStr>,
+}
+
+/// The command a `system` message responds to, with its fields.
+///
+/// Responses to commands this crate does not know about, or whose fields do not have the expected
+/// shape, are kept in [`SystemResponse::Unknown`] instead of failing to decode the whole message.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(from = "Map<String, Value>", into = "Map<String, Value>")]
+pub enum SystemResponse {
+    LedCtrl(LedCtrl),
+    GetAccessories(Accessories),
+    SetAccessories(Accessories),
+    GetAccessCode(AccessCode),
+    /// A command this crate does not know about yet, with all its fields including `command`.
+    Unknown(Map<String, Value>),
+}
+
+impl SystemResponse {
+    /// The `command` field, e.g. `ledctrl`.
+    pub fn command(&self) -> &str {
+        match self {
+            SystemResponse::LedCtrl(_) => "ledctrl",
+            SystemResponse::GetAccessories(_) => "get_accessories",
+            SystemResponse::SetAccessories(_) => "set_accessories",
+            SystemResponse::GetAccessCode(_) => "get_access_code",
+            SystemResponse::Unknown(fields) => fields
+                .get("command")
+                .and_then(Value::as_str)
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl From<Map<String, Value>> for SystemResponse {
+    fn from(mut fields: Map<String, Value>) -> Self {
+        let Some(Value::String(command)) = fields.remove("command") else {
+            return SystemResponse::Unknown(fields);
+        };
+        fn decode<T: DeserializeOwned>(fields: &Map<String, Value>) -> Option<T> {
+            T::deserialize(fields).ok()
+        }
+        let known = match command.as_str() {
+            "ledctrl" => decode(&fields).map(SystemResponse::LedCtrl),
+            "get_accessories" => decode(&fields).map(SystemResponse::GetAccessories),
+            "set_accessories" => decode(&fields).map(SystemResponse::SetAccessories),
+            "get_access_code" => decode(&fields).map(SystemResponse::GetAccessCode),
+            _ => None,
+        };
+        known.unwrap_or_else(|| {
+            fields.insert("command".into(), Value::String(command));
+            SystemResponse::Unknown(fields)
+        })
+    }
+}
+
+impl From<SystemResponse> for Map<String, Value> {
+    fn from(response: SystemResponse) -> Self {
+        let command = response.command().to_owned();
+        let fields = match response {
+            SystemResponse::LedCtrl(led) => serde_json::to_value(led),
+            SystemResponse::GetAccessories(accessories)
+            | SystemResponse::SetAccessories(accessories) => serde_json::to_value(accessories),
+            SystemResponse::GetAccessCode(access_code) => serde_json::to_value(access_code),
+            SystemResponse::Unknown(fields) => return fields,
+        };
+        // NOTE: These are plain structs of strings and numbers, which always serialize to objects.
+        let Ok(Value::Object(mut fields)) = fields else {
+            unreachable!()
+        };
+        fields.insert("command".into(), Value::String(command));
+        fields
+    }
+}
+
+/// The response to `get_access_code`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AccessCode {
+    /// The LAN access code, used as MQTT, FTP and camera password.
+    pub access_code: SmolStr,
+    #[serde(flatten)]
+    pub unknown: BTreeMap<SmolStr, Value>,
+}
+
+/// Accessories of the printer, in the responses to `get_accessories` and `set_accessories`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Accessories {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accessory_type: Option<Reported<AccessoryType>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nozzle_type: Option<NozzleType>,
+    /// In mm.
+    #[serde(
+        default,
+        deserialize_with = "number_or_string",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub nozzle_diameter: Option<f64>,
+    #[serde(flatten)]
+    pub unknown: BTreeMap<SmolStr, Value>,
+}
+
+/// The firmware reports some numbers as strings, e.g. `"0.4"`.
+fn number_or_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<f64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString {
+        Number(f64),
+        String(SmolStr),
+    }
+
+    match Option::<NumberOrString>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(NumberOrString::Number(number)) => Ok(Some(number)),
+        Some(NumberOrString::String(string)) => string
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
 }
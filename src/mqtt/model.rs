@@ -0,0 +1,263 @@
+//! Printer models and their capabilities.
+use std::fmt;
+
+use super::{
+    command::{
+        gcode::{Axis, Fan, Heater},
+        system::LedNode,
+    },
+    message::ams::TrayLocation,
+};
+
+/// A BambuLab printer model, derived from the first three characters of its serial number.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PrinterModel {
+    X1,
+    X1C,
+    X1E,
+    P1P,
+    P1S,
+    A1,
+    A1Mini,
+    /// A model this crate does not know about yet. Conservative limits are used.
+    Unknown,
+}
+
+impl PrinterModel {
+    pub fn from_serial(serial: &str) -> Self {
+        match serial.get(..3) {
+            Some("00W") => PrinterModel::X1,
+            Some("00M") => PrinterModel::X1C,
+            Some("03W") => PrinterModel::X1E,
+            Some("01S") => PrinterModel::P1P,
+            Some("01P") => PrinterModel::P1S,
+            Some("039") => PrinterModel::A1,
+            Some("030") => PrinterModel::A1Mini,
+            _ => PrinterModel::Unknown,
+        }
+    }
+
+    /// The model reported in the `project_name` of the modules returned by `get_version`, e.g.
+    /// `N2S` for the A1.
+    pub fn from_project_name(project_name: &str) -> Self {
+        match project_name {
+            "BL-P002" => PrinterModel::X1,
+            "BL-P001" => PrinterModel::X1C,
+            "C13" => PrinterModel::X1E,
+            "C11" => PrinterModel::P1P,
+            "C12" => PrinterModel::P1S,
+            "N2S" => PrinterModel::A1,
+            "N1" => PrinterModel::A1Mini,
+            _ => PrinterModel::Unknown,
+        }
+    }
+
+    /// The features and limits of the model.
+    ///
+    /// For [`PrinterModel::Unknown`], limits are conservative and neither a chamber heater, an RTSP
+    /// camera nor an AMS lite is assumed. Fans and the Micro Lidar are assumed present, leaving it
+    /// to the firmware to ignore commands for them.
+    pub fn capabilities(self) -> Capabilities {
+        use PrinterModel::*;
+
+        let x1 = matches!(self, X1 | X1C | X1E);
+        let a1 = matches!(self, A1 | A1Mini);
+        // All current models have a cubic build volume.
+        let travel = match self {
+            A1Mini | Unknown => 180.0,
+            _ => 256.0,
+        };
+        Capabilities {
+            max_nozzle_temperature: 300,
+            max_bed_temperature: match self {
+                X1E => 120,
+                X1 | X1C => 110,
+                A1Mini => 80,
+                P1P | P1S | A1 | Unknown => 100,
+            },
+            max_chamber_temperature: match self {
+                X1E => Some(60),
+                _ => None,
+            },
+            travel_x: travel,
+            travel_y: travel,
+            travel_z: travel,
+            auxiliary_fan: !a1,
+            chamber_fan: !a1 && self != P1P,
+            lidar: x1 || self == Unknown,
+            ams_lite: a1,
+            camera: if x1 {
+                CameraStream::Rtsp
+            } else {
+                CameraStream::Jpeg
+            },
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PrinterModel::X1 => "X1",
+            PrinterModel::X1C => "X1 Carbon",
+            PrinterModel::X1E => "X1E",
+            PrinterModel::P1P => "P1P",
+            PrinterModel::P1S => "P1S",
+            PrinterModel::A1 => "A1",
+            PrinterModel::A1Mini => "A1 mini",
+            PrinterModel::Unknown => "unknown model",
+        }
+    }
+}
+
+impl fmt::Display for PrinterModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What a printer model supports, consulted by [`MqttClient`] to reject operations the
+/// firmware would ignore with [`MqttError::Unsupported`].
+///
+/// [`MqttClient`]: crate::MqttClient
+/// [`MqttError::Unsupported`]: crate::MqttError::Unsupported
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Capabilities {
+    /// Highest target temperatures accepted in Celsius.
+    pub max_nozzle_temperature: u16,
+    pub max_bed_temperature: u16,
+    /// `None` if the printer has no chamber heater.
+    pub max_chamber_temperature: Option<u16>,
+    /// Length of travel along each axis in mm, which is also the highest position the toolhead
+    /// or bed can be moved to.
+    pub travel_x: f64,
+    pub travel_y: f64,
+    pub travel_z: f64,
+    /// The auxiliary part cooling fan on the side of the chamber.
+    pub auxiliary_fan: bool,
+    /// The chamber exhaust fan.
+    pub chamber_fan: bool,
+    /// The Micro Lidar, used for first layer inspection. Its work light is a separate LED node.
+    pub lidar: bool,
+    /// Whether the printer uses the AMS lite instead of the AMS, limiting it to one unit.
+    pub ams_lite: bool,
+    pub camera: CameraStream,
+}
+
+impl Capabilities {
+    /// Highest target temperature accepted for `heater` in Celsius, or `None` if the printer
+    /// cannot control it.
+    pub fn max_temperature(&self, heater: Heater) -> Option<u16> {
+        match heater {
+            Heater::Nozzle => Some(self.max_nozzle_temperature),
+            Heater::Bed => Some(self.max_bed_temperature),
+            Heater::Chamber => self.max_chamber_temperature,
+        }
+    }
+
+    /// Length of travel along `axis` in mm.
+    pub fn travel_limit(&self, axis: Axis) -> f64 {
+        match axis {
+            Axis::X => self.travel_x,
+            Axis::Y => self.travel_y,
+            Axis::Z => self.travel_z,
+        }
+    }
+
+    /// Whether the printer has `fan`.
+    pub fn has_fan(&self, fan: Fan) -> bool {
+        match fan {
+            Fan::Part | Fan::Heatbreak => true,
+            Fan::Auxiliary => self.auxiliary_fan,
+            Fan::Chamber => self.chamber_fan,
+        }
+    }
+
+    /// How many AMS units can be connected: a single AMS lite, or up to four AMS.
+    pub fn max_ams_units(&self) -> u8 {
+        if self.ams_lite {
+            1
+        } else {
+            4
+        }
+    }
+
+    /// Whether the printer can have a tray at `location`.
+    pub fn has_tray(&self, location: TrayLocation) -> bool {
+        match location {
+            TrayLocation::Ams { unit, tray } => unit < self.max_ams_units() && tray < 4,
+            TrayLocation::External => true,
+        }
+    }
+
+    /// Whether the printer has the light `node`.
+    pub fn has_light(&self, node: LedNode) -> bool {
+        match node {
+            LedNode::ChamberLight => true,
+            LedNode::WorkLight => self.lidar,
+        }
+    }
+}
+
+/// How the built-in camera is streamed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum CameraStream {
+    /// RTSP over TLS on port 322, advertised in `ipcam.rtsp_url`. The resolution can be changed.
+    Rtsp,
+    /// JPEG frames over TLS on port 6000, read by [`CameraClient`].
+    ///
+    /// [`CameraClient`]: crate::CameraClient
+    Jpeg,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Axis, CameraStream, Fan, Heater, LedNode, PrinterModel, TrayLocation};
+
+    #[test]
+    fn model_from_serial() {
+        assert_eq!(
+            PrinterModel::from_serial("00M09A350100123"),
+            PrinterModel::X1C
+        );
+        assert_eq!(
+            PrinterModel::from_serial("01S00C000000000"),
+            PrinterModel::P1P
+        );
+        assert_eq!(
+            PrinterModel::from_serial("01P00A999999999"),
+            PrinterModel::P1S
+        );
+        assert_eq!(
+            PrinterModel::from_serial("0309DA000000000"),
+            PrinterModel::A1Mini
+        );
+        assert_eq!(PrinterModel::from_serial("XYZ"), PrinterModel::Unknown);
+        assert_eq!(PrinterModel::from_serial(""), PrinterModel::Unknown);
+        assert_eq!(PrinterModel::from_project_name("N2S"), PrinterModel::A1);
+        assert_eq!(PrinterModel::from_project_name("C12"), PrinterModel::P1S);
+        assert_eq!(PrinterModel::from_project_name(""), PrinterModel::Unknown);
+    }
+
+    #[test]
+    fn capabilities() {
+        let a1_mini = PrinterModel::A1Mini.capabilities();
+        assert_eq!(a1_mini.max_temperature(Heater::Bed), Some(80));
+        assert_eq!(a1_mini.travel_limit(Axis::Z), 180.0);
+        assert!(a1_mini.ams_lite);
+        assert!(a1_mini.has_tray(TrayLocation::Ams { unit: 0, tray: 3 }));
+        assert!(!a1_mini.has_tray(TrayLocation::Ams { unit: 1, tray: 0 }));
+        assert!(!a1_mini.has_fan(Fan::Auxiliary));
+        assert_eq!(a1_mini.camera, CameraStream::Jpeg);
+
+        let x1e = PrinterModel::X1E.capabilities();
+        assert_eq!(x1e.max_temperature(Heater::Chamber), Some(60));
+        assert!(x1e.has_light(LedNode::WorkLight));
+        assert_eq!(x1e.camera, CameraStream::Rtsp);
+
+        let p1s = PrinterModel::P1S.capabilities();
+        assert_eq!(p1s.max_temperature(Heater::Chamber), None);
+        assert!(p1s.has_fan(Fan::Chamber));
+        assert!(!p1s.lidar);
+        assert!(p1s.has_tray(TrayLocation::Ams { unit: 3, tray: 3 }));
+        assert!(!PrinterModel::P1P.capabilities().has_fan(Fan::Chamber));
+    }
+}
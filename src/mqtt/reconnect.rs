@@ -0,0 +1,155 @@
+//! Reconnection policy for the MQTT client.
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::Duration,
+};
+
+/// Controls how the background task of [`MqttClient`](super::MqttClient) reconnects after the
+/// connection to the printer is lost.
+///
+/// The delay before attempt `n` (starting at 1) is `initial_delay * multiplier^(n - 1)`, capped
+/// at `max_delay`, and then randomly shortened by up to `jitter` (a fraction between 0 and 1) so
+/// that several clients do not reconnect in lockstep.
+///
+/// A `multiplier` below 1 or a `jitter` that is not a number is ignored, so that the delay never
+/// shrinks between attempts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectPolicy {
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    pub multiplier: f64,
+    /// Give up after this many consecutive failed attempts. `None` retries forever.
+    pub max_attempts: Option<u32>,
+    pub jitter: f64,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(60),
+            multiplier: 2.0,
+            max_attempts: None,
+            jitter: 0.1,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// A policy that never reconnects.
+    pub fn never() -> Self {
+        Self {
+            max_attempts: Some(0),
+            ..Default::default()
+        }
+    }
+
+    /// The delay before reconnection attempt number `attempt` (starting at 1), or `None` if the
+    /// client should give up.
+    pub(crate) fn delay(&self, attempt: u32) -> Option<Duration> {
+        if self.max_attempts.is_some_and(|max| attempt > max) {
+            return None;
+        }
+
+        // NOTE: Written so that NaN falls back to the defaults as well.
+        let multiplier = if self.multiplier >= 1.0 {
+            self.multiplier
+        } else {
+            1.0
+        };
+        let jitter = if self.jitter > 0.0 {
+            self.jitter.min(1.0)
+        } else {
+            0.0
+        };
+
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as u32) as i32;
+        let delay = (self.initial_delay.as_secs_f64() * multiplier.powi(exponent))
+            .min(self.max_delay.as_secs_f64());
+        let delay = delay * (1.0 - jitter * random_fraction());
+        // Rounding can push a `max_delay` close to `Duration::MAX` out of range.
+        Some(Duration::try_from_secs_f64(delay).unwrap_or(self.max_delay))
+    }
+}
+
+/// A random number in `[0, 1)`. Good enough for spreading out reconnection attempts.
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u8(0);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::ReconnectPolicy;
+
+    #[test]
+    fn exponential_backoff() {
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            multiplier: 2.0,
+            max_attempts: Some(6),
+            jitter: 0.0,
+        };
+        assert_eq!(policy.delay(1), Some(Duration::from_millis(100)));
+        assert_eq!(policy.delay(2), Some(Duration::from_millis(200)));
+        assert_eq!(policy.delay(4), Some(Duration::from_millis(800)));
+        assert_eq!(policy.delay(5), Some(Duration::from_secs(1)));
+        assert_eq!(policy.delay(6), Some(Duration::from_secs(1)));
+        assert_eq!(policy.delay(7), None);
+    }
+
+    #[test]
+    fn jitter_shortens_delay() {
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::from_secs(10),
+            jitter: 0.5,
+            ..Default::default()
+        };
+        for _ in 0..100 {
+            let delay = policy.delay(1).unwrap();
+            assert!(delay > Duration::from_secs(5) && delay <= Duration::from_secs(10));
+        }
+    }
+
+    #[test]
+    fn negative_multiplier_is_ignored() {
+        let policy = ReconnectPolicy {
+            multiplier: -2.0,
+            jitter: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(policy.delay(2), Some(Duration::from_secs(1)));
+        assert_eq!(policy.delay(3), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn nan_jitter_is_ignored() {
+        let policy = ReconnectPolicy {
+            jitter: f64::NAN,
+            ..Default::default()
+        };
+        assert_eq!(policy.delay(1), Some(Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn huge_max_delay_does_not_overflow() {
+        let policy = ReconnectPolicy {
+            initial_delay: Duration::MAX,
+            max_delay: Duration::MAX,
+            jitter: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(policy.delay(1), Some(Duration::MAX));
+        assert_eq!(policy.delay(100), Some(Duration::MAX));
+    }
+
+    #[test]
+    fn never_reconnects() {
+        assert_eq!(ReconnectPolicy::never().delay(1), None);
+    }
+}
@@ -0,0 +1,313 @@
+//! Printer state assembled from `push_status` reports.
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use smol_str::SmolStr;
+
+use super::message::print::Print;
+
+/// The current state of the printer.
+///
+/// After a `pushall` the printer sends a full report, and from then on P1 and A1 printers only
+/// send the fields that changed. Each report is merged on top of the previous state, so
+/// [`PrinterState::status`] always holds the latest known value of every field.
+#[derive(Debug, Clone, Default)]
+pub struct PrinterState {
+    /// Merged JSON payload of all reports received so far.
+    merged: Map<String, Value>,
+    /// Typed view of `merged`.
+    status: Print,
+    /// When each top-level field was last reported.
+    updated: HashMap<SmolStr, DateTime<Utc>>,
+    /// When the last full report was received.
+    full_report: Option<DateTime<Utc>>,
+}
+
+impl PrinterState {
+    /// The merged printer status.
+    pub fn status(&self) -> &Print {
+        &self.status
+    }
+
+    /// When `field` (a top-level key of the `push_status` report, e.g. `"bed_temper"`) was last
+    /// reported by the printer.
+    pub fn last_updated(&self, field: &str) -> Option<DateTime<Utc>> {
+        self.updated.get(field).copied()
+    }
+
+    /// When any field was last reported by the printer.
+    pub fn last_report(&self) -> Option<DateTime<Utc>> {
+        self.updated.values().max().copied()
+    }
+
+    /// When the printer last sent a full report (`"msg": 0`), e.g. in response to `pushall`.
+    pub fn last_full_report(&self) -> Option<DateTime<Utc>> {
+        self.full_report
+    }
+
+    /// Returns `true` if no report has been received yet.
+    pub fn is_empty(&self) -> bool {
+        self.merged.is_empty()
+    }
+
+    /// Merge a report into the state. On error the state is left unchanged.
+    ///
+    /// The report is merged into the JSON payload in place, and the whole payload is then decoded
+    /// again. Nested objects such as `ams` or `ipcam` are reported partially as well, so their
+    /// typed view can only be rebuilt from the merged JSON, and serde cannot decode into only
+    /// some fields of an existing [`Print`]. Decoding borrows the payload rather than copying it.
+    pub(crate) fn apply(&mut self, report: &Print, now: DateTime<Utc>) -> serde_json::Result<()> {
+        let Value::Object(delta) = serde_json::to_value(report)? else {
+            unreachable!("Print always serializes to an object");
+        };
+
+        // A full report lists every AMS unit and tray, so its arrays replace the previous ones.
+        let by_id = report.msg != Some(0);
+        // Only the fields in the report change, so only those are needed to roll back.
+        let mut previous = Vec::with_capacity(delta.len());
+        for (key, value) in delta {
+            previous.push((key.clone(), self.merged.get(&key).cloned()));
+            merge(self.merged.entry(key).or_insert(Value::Null), value, by_id);
+        }
+
+        match Print::deserialize(&self.merged) {
+            Ok(status) => self.status = status,
+            Err(err) => {
+                for (key, value) in previous {
+                    match value {
+                        Some(value) => self.merged.insert(key, value),
+                        None => self.merged.remove(&key),
+                    };
+                }
+                return Err(err);
+            }
+        }
+        for (field, _) in previous {
+            self.updated.insert(SmolStr::new(field), now);
+        }
+        if report.msg == Some(0) {
+            self.full_report = Some(now);
+        }
+        Ok(())
+    }
+}
+
+/// Deep merge `delta` into `target`. Objects are merged key by key.
+///
+/// P1 and A1 printers only report the AMS units and trays that changed, with their `id` and the
+/// changed fields. If `by_id` is set, arrays whose elements all have an `id` are therefore merged
+/// element by element, and elements with a new `id` are appended. Everything else is replaced.
+fn merge(target: &mut Value, delta: Value, by_id: bool) {
+    match (target, delta) {
+        (Value::Object(target), Value::Object(delta)) => {
+            for (key, value) in delta {
+                merge(target.entry(key).or_insert(Value::Null), value, by_id);
+            }
+        }
+        (Value::Array(target), Value::Array(delta))
+            if by_id && has_ids(target) && has_ids(&delta) =>
+        {
+            for element in delta {
+                match target.iter_mut().find(|other| other["id"] == element["id"]) {
+                    Some(other) => merge(other, element, by_id),
+                    None => target.push(element),
+                }
+            }
+        }
+        (target, delta) => *target = delta,
+    }
+}
+
+/// Whether `array` is a non-empty array of objects that all have an `id`.
+fn has_ids(array: &[Value]) -> bool {
+    !array.is_empty() && array.iter().all(|element| element.get("id").is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    use super::PrinterState;
+    use crate::mqtt::{
+        command::camera::Toggle,
+        message::{
+            ams::TrayLocation,
+            print::{GcodeState, Print},
+            Message,
+        },
+    };
+
+    const X1C_PUSH_STATUS: &str = include_str!("message/testdata/x1c_push_status.json");
+    const AMS_TRAY_DELTA: &str = include_str!("message/testdata/ams_tray_delta.json");
+
+    fn report(value: serde_json::Value) -> Print {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn decode(payload: &str) -> Print {
+        let message: Message = serde_json::from_str(payload).unwrap();
+        Print::try_from(message).unwrap()
+    }
+
+    #[test]
+    fn merges_deltas_on_top_of_snapshot() {
+        let first = Utc.with_ymd_and_hms(2025, 1, 23, 10, 0, 0).unwrap();
+        let second = Utc.with_ymd_and_hms(2025, 1, 23, 10, 0, 1).unwrap();
+
+        let mut state = PrinterState::default();
+        assert!(state.is_empty());
+
+        state
+            .apply(
+                &report(json!({
+                    "command": "push_status",
+                    "sequence_id": "1",
+                    "msg": 0,
+                    "bed_temper": 24.0,
+                    "nozzle_temper": 25.0,
+                    "gcode_state": "IDLE",
+                    "ipcam": {"ipcam_record": "enable", "timelapse": "disable"},
+                    "ams": {"tray_now": "255", "version": 2}
+                })),
+                first,
+            )
+            .unwrap();
+
+        state
+            .apply(
+                &report(json!({
+                    "command": "push_status",
+                    "sequence_id": "2",
+                    "msg": 1,
+                    "nozzle_temper": 180.5,
+                    "gcode_state": "PREPARE",
+                    "ipcam": {"timelapse": "enable"},
+                    "ams": {"tray_now": "0"}
+                })),
+                second,
+            )
+            .unwrap();
+
+        let status = state.status();
+        assert_eq!(status.sequence_id, "2");
+        assert_eq!(status.bed_temper, Some(24.0));
+        assert_eq!(status.nozzle_temper, Some(180.5));
+        assert_eq!(status.gcode_state, Some(GcodeState::Prepare));
+
+        let ipcam = status.ipcam.as_ref().unwrap();
+        assert_eq!(ipcam.ipcam_record, Some(Toggle::Enable));
+        assert_eq!(ipcam.timelapse, Some(Toggle::Enable));
+        let ams = status.ams.as_ref().unwrap();
+        assert_eq!(ams.tray_now.as_deref(), Some("0"));
+        assert_eq!(ams.version, Some(2));
+
+        assert_eq!(state.last_updated("bed_temper"), Some(first));
+        assert_eq!(state.last_updated("nozzle_temper"), Some(second));
+        assert_eq!(state.last_updated("chamber_temper"), None);
+        assert_eq!(state.last_report(), Some(second));
+        assert_eq!(state.last_full_report(), Some(first));
+    }
+
+    #[test]
+    fn merges_ams_trays_by_id() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 23, 10, 0, 0).unwrap();
+        let mut state = PrinterState::default();
+        state
+            .apply(
+                &report(json!({
+                    "command": "push_status",
+                    "sequence_id": "1",
+                    "msg": 0,
+                    "ams": {"ams": [
+                        {"id": "0", "humidity": "4", "tray": [
+                            {"id": "0", "tray_type": "PLA", "remain": 80},
+                            {"id": "1", "tray_type": "PETG", "remain": 50},
+                            {"id": "2", "tray_type": "ABS", "remain": 20}
+                        ]},
+                        {"id": "1", "humidity": "3", "tray": [{"id": "0", "tray_type": "TPU"}]}
+                    ]}
+                })),
+                now,
+            )
+            .unwrap();
+        state
+            .apply(
+                &report(json!({
+                    "command": "push_status",
+                    "sequence_id": "2",
+                    "msg": 1,
+                    "ams": {"ams": [{"id": "0", "tray": [{"id": "2", "remain": 19}]}]}
+                })),
+                now,
+            )
+            .unwrap();
+
+        let ams = state.status().ams.as_ref().unwrap();
+        assert_eq!(ams.ams.as_ref().unwrap().len(), 2);
+        let unit = ams.unit(0).unwrap();
+        assert_eq!(unit.humidity_level(), Some(4));
+        assert_eq!(unit.tray.as_ref().unwrap().len(), 3);
+        assert_eq!(unit.tray(0).unwrap().remaining(), Some(80));
+        assert_eq!(unit.tray(1).unwrap().tray_type.as_deref(), Some("PETG"));
+        let tray = unit.tray(2).unwrap();
+        assert_eq!(tray.tray_type.as_deref(), Some("ABS"));
+        assert_eq!(tray.remaining(), Some(19));
+        let other = ams.unit(1).unwrap().tray(0).unwrap();
+        assert_eq!(other.tray_type.as_deref(), Some("TPU"));
+
+        // A full report replaces the units, e.g. after one is disconnected.
+        state
+            .apply(
+                &report(json!({
+                    "command": "push_status",
+                    "sequence_id": "3",
+                    "msg": 0,
+                    "ams": {"ams": [{"id": "0", "tray": [{"id": "0"}]}]}
+                })),
+                now,
+            )
+            .unwrap();
+        let ams = state.status().ams.as_ref().unwrap();
+        assert_eq!(ams.unit(1), None);
+        assert!(ams.unit(0).unwrap().tray(0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn active_tray_survives_partial_tray_report() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 23, 10, 0, 0).unwrap();
+        let mut state = PrinterState::default();
+        state.apply(&decode(X1C_PUSH_STATUS), now).unwrap();
+        state.apply(&decode(AMS_TRAY_DELTA), now).unwrap();
+
+        let status = state.status();
+        let ams = status.ams.as_ref().unwrap();
+        assert_eq!(ams.tray_now(), Some(TrayLocation::Ams { unit: 0, tray: 2 }));
+        let active = status.active_tray().unwrap();
+        assert_eq!(active.tray_type.as_deref(), Some("PETG"));
+        assert_eq!(active.remaining(), Some(42));
+
+        let unit = ams.unit(0).unwrap();
+        assert_eq!(unit.tray.as_ref().unwrap().len(), 4);
+        assert_eq!(unit.humidity_level(), Some(4));
+        assert_eq!(unit.tray(0).unwrap().remaining(), Some(87));
+    }
+
+    #[test]
+    fn failed_merge_leaves_state_unchanged() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 23, 10, 0, 0).unwrap();
+        let mut state = PrinterState::default();
+        state.merged.insert("bed_temper".into(), json!("hot"));
+
+        let delta = report(json!({
+            "command": "push_status",
+            "sequence_id": "1",
+            "nozzle_temper": 25.0
+        }));
+        assert!(state.apply(&delta, now).is_err());
+        assert_eq!(state.merged.len(), 1);
+        assert_eq!(state.last_updated("nozzle_temper"), None);
+    }
+}
@@ -0,0 +1,130 @@
+//! A minimal MQTT broker stand-in for testing [`MqttClient`](super::MqttClient) without a printer.
+use bytes::BytesMut;
+use rumqttc::{
+    mqttbytes::{self, v4},
+    ConnAck, ConnectReturnCode, Packet, Publish, QoS, SubAck, SubscribeReasonCode,
+};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use super::{builder::MqttTransport, MqttClient};
+
+pub(crate) const SERIAL: &str = "01S00C000000000";
+
+const MAX_PACKET_SIZE: usize = 1024 * 1024;
+
+/// Accepts connections from a plaintext [`MqttClient`] on a random local port.
+pub(crate) struct FakeBroker {
+    listener: TcpListener,
+}
+
+impl FakeBroker {
+    pub(crate) async fn bind() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        Self { listener }
+    }
+
+    /// A client configured to connect to this broker.
+    pub(crate) fn client(&self) -> MqttClient {
+        MqttClient::builder("127.0.0.1", "12345678", SERIAL)
+            .port(self.listener.local_addr().unwrap().port())
+            .transport(MqttTransport::Tcp)
+            .build()
+    }
+
+    /// Accept a connection and answer its CONNECT and SUBSCRIBE packets.
+    pub(crate) async fn accept(&self) -> BrokerConnection {
+        let (stream, _) = self.listener.accept().await.unwrap();
+        let mut connection = BrokerConnection {
+            stream,
+            buffer: BytesMut::new(),
+        };
+
+        match connection.read_packet().await {
+            Some(Packet::Connect(_)) => {}
+            other => panic!("Expected CONNECT, got {other:?}"),
+        }
+        connection
+            .write_packet(|buffer| ConnAck::new(ConnectReturnCode::Success, false).write(buffer))
+            .await;
+
+        match connection.read_packet().await {
+            Some(Packet::Subscribe(subscribe)) => {
+                assert_eq!(subscribe.filters[0].path, format!("device/{SERIAL}/report"));
+                connection
+                    .write_packet(|buffer| {
+                        SubAck::new(
+                            subscribe.pkid,
+                            vec![SubscribeReasonCode::Success(QoS::AtMostOnce)],
+                        )
+                        .write(buffer)
+                    })
+                    .await;
+            }
+            other => panic!("Expected SUBSCRIBE, got {other:?}"),
+        }
+
+        connection
+    }
+}
+
+/// A connection accepted by [`FakeBroker`].
+pub(crate) struct BrokerConnection {
+    stream: TcpStream,
+    buffer: BytesMut,
+}
+
+impl BrokerConnection {
+    /// Read the next packet, or `None` if the client closed the connection.
+    async fn read_packet(&mut self) -> Option<Packet> {
+        loop {
+            match v4::read(&mut self.buffer, MAX_PACKET_SIZE) {
+                Ok(packet) => return Some(packet),
+                Err(mqttbytes::Error::InsufficientBytes(_)) => {}
+                Err(err) => panic!("Invalid packet: {err:?}"),
+            }
+            if self.stream.read_buf(&mut self.buffer).await.unwrap() == 0 {
+                return None;
+            }
+        }
+    }
+
+    async fn write_packet(
+        &mut self,
+        write: impl FnOnce(&mut BytesMut) -> Result<usize, mqttbytes::Error>,
+    ) {
+        let mut buffer = BytesMut::new();
+        write(&mut buffer).unwrap();
+        self.stream.write_all(&buffer).await.unwrap();
+    }
+
+    /// Wait for the next command published by the client.
+    pub(crate) async fn next_request(&mut self) -> Value {
+        loop {
+            match self.read_packet().await {
+                Some(Packet::Publish(publish)) => {
+                    assert_eq!(publish.topic, format!("device/{SERIAL}/request"));
+                    return serde_json::from_slice(&publish.payload).unwrap();
+                }
+                Some(Packet::PingReq) => {
+                    self.write_packet(|buffer| v4::PingResp.write(buffer)).await;
+                }
+                Some(_) => {}
+                None => panic!("Client disconnected"),
+            }
+        }
+    }
+
+    /// Publish `payload` as a report from the printer.
+    pub(crate) async fn publish(&mut self, payload: Value) {
+        let publish = Publish::new(
+            format!("device/{SERIAL}/report"),
+            QoS::AtMostOnce,
+            serde_json::to_vec(&payload).unwrap(),
+        );
+        self.write_packet(|buffer| publish.write(buffer)).await;
+    }
+}
@@ -1,57 +1,452 @@
+use std::sync::{Arc, Mutex};
+
+use ::ring::digest::{digest, SHA256};
+use rumqttc::tokio_rustls::rustls as rumqttc_rustls;
+use smol_str::SmolStr;
 use tokio_rustls::rustls::{
-    client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
-    pki_types::{CertificateDer, ServerName, UnixTime},
-    DigitallySignedStruct, Error, SignatureScheme,
+    client::{
+        danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        verify_server_cert_signed_by_trust_anchor,
+    },
+    crypto::{ring, verify_tls12_signature, verify_tls13_signature},
+    pki_types::{pem::PemObject, CertificateDer, ServerName, UnixTime},
+    server::ParsedCertificate,
+    CertificateError, ClientConfig, DigitallySignedStruct, Error, RootCertStore, SignatureScheme,
 };
+use webpki::EndEntityCert;
+
+/// How to verify the certificate presented by the printer.
+///
+/// The same policy can be shared by [`MqttClient`](crate::MqttClient),
+/// [`CameraClient`](crate::CameraClient) and [`FileClient`](crate::FileClient). Clones share
+/// the pinned fingerprint, so the first client to connect pins it for all of them. All clients
+/// verify certificates and handshake signatures with the *ring* crypto provider.
+///
+/// # Security
+///
+/// The default policy is [`CertificatePolicy::Insecure`], which accepts any certificate, so
+/// anyone on the network path can impersonate the printer and read the access code. Use
+/// [`CertificatePolicy::ca_pem`] with the BambuLab CA bundle, or
+/// [`CertificatePolicy::trust_on_first_use`], to verify the printer. This crate does not ship
+/// the BambuLab CA bundle.
+#[derive(Debug, Clone, Default)]
+pub enum CertificatePolicy {
+    /// Accept any certificate. This is the default, see [Security](CertificatePolicy#security).
+    #[default]
+    Insecure,
+    /// Require a certificate issued by a trusted CA with the printer serial number as its
+    /// common name.
+    Ca(CertificateAuthority),
+    /// Require a certificate matching a pinned fingerprint.
+    Pinned(CertificatePin),
+}
+
+impl CertificatePolicy {
+    /// Require a certificate issued by one of `roots` with `serial` as its common name.
+    ///
+    /// `roots` is usually the BambuLab CA bundle shipped with Bambu Studio (`printer.cer`).
+    /// This crate does not ship that bundle, so it has to be loaded by the caller, see
+    /// [`ca_pem`](Self::ca_pem).
+    pub fn ca(
+        roots: impl IntoIterator<Item = CertificateDer<'static>>,
+        serial: impl Into<SmolStr>,
+    ) -> Result<Self, Error> {
+        let mut store = RootCertStore::empty();
+        for root in roots {
+            store.add(root)?;
+        }
+        Ok(Self::Ca(CertificateAuthority {
+            roots: Arc::new(store),
+            serial: serial.into(),
+        }))
+    }
+
+    /// Same as [`ca`](Self::ca), with the roots read from a PEM bundle such as `printer.cer`.
+    pub fn ca_pem(pem: &[u8], serial: impl Into<SmolStr>) -> Result<Self, Error> {
+        let roots = CertificateDer::pem_slice_iter(pem)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| Error::General(format!("Invalid CA bundle: {err}")))?;
+        if roots.is_empty() {
+            return Err(Error::General("Empty CA bundle".to_string()));
+        }
+        Self::ca(roots, serial)
+    }
+
+    /// Pin the certificate presented on the first connection and reject any other one after.
+    pub fn trust_on_first_use() -> Self {
+        Self::Pinned(CertificatePin::default())
+    }
+
+    /// Require a certificate with the given SHA-256 fingerprint.
+    pub fn pinned(fingerprint: [u8; 32]) -> Self {
+        Self::Pinned(CertificatePin::new(fingerprint))
+    }
+
+    fn is_insecure(&self) -> bool {
+        matches!(self, CertificatePolicy::Insecure)
+    }
+
+    /// Verify the certificate chain presented by the printer.
+    ///
+    /// This is independent of the rustls version, so it backs [`PrinterVerifier`] for both
+    /// `rumqttc` and this crate.
+    pub(crate) fn verify_certificate(
+        &self,
+        end_entity: &CertificateDer,
+        intermediates: &[CertificateDer],
+        now: UnixTime,
+    ) -> Result<(), Error> {
+        match self {
+            CertificatePolicy::Insecure => Ok(()),
+            CertificatePolicy::Ca(authority) => {
+                let cert = ParsedCertificate::try_from(end_entity)?;
+                verify_server_cert_signed_by_trust_anchor(
+                    &cert,
+                    &authority.roots,
+                    intermediates,
+                    now,
+                    ring::default_provider()
+                        .signature_verification_algorithms
+                        .all,
+                )?;
+                match subject_common_name(end_entity) {
+                    Some(common_name) if common_name == authority.serial => Ok(()),
+                    _ => Err(CertificateError::NotValidForName.into()),
+                }
+            }
+            CertificatePolicy::Pinned(pin) => pin.verify(end_entity),
+        }
+    }
+}
+
+/// Trusted CA certificates and the expected printer serial number.
+#[derive(Debug, Clone)]
+pub struct CertificateAuthority {
+    roots: Arc<RootCertStore>,
+    serial: SmolStr,
+}
+
+/// SHA-256 fingerprint of the printer certificate.
+#[derive(Debug, Clone, Default)]
+pub struct CertificatePin(Arc<Mutex<Option<[u8; 32]>>>);
 
+impl CertificatePin {
+    /// Pin a known fingerprint.
+    pub fn new(fingerprint: [u8; 32]) -> Self {
+        Self(Arc::new(Mutex::new(Some(fingerprint))))
+    }
+
+    /// The pinned fingerprint, or `None` if no connection was made yet. Persist it and pass it
+    /// to [`CertificatePolicy::pinned`] to keep trusting the same certificate across restarts.
+    pub fn fingerprint(&self) -> Option<[u8; 32]> {
+        *self.0.lock().unwrap()
+    }
+
+    fn verify(&self, end_entity: &CertificateDer) -> Result<(), Error> {
+        let fingerprint: [u8; 32] = digest(&SHA256, end_entity)
+            .as_ref()
+            .try_into()
+            .expect("SHA-256 digest is 32 bytes");
+        let mut pinned = self.0.lock().unwrap();
+        match *pinned {
+            Some(pinned) if pinned == fingerprint => Ok(()),
+            Some(_) => Err(Error::General(
+                "Printer certificate does not match the pinned fingerprint".to_string(),
+            )),
+            None => {
+                *pinned = Some(fingerprint);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Create a rustls configuration verifying the printer certificate according to `policy`.
+pub(crate) fn client_config(policy: &CertificatePolicy) -> ClientConfig {
+    ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .expect("ring supports the default protocol versions")
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(PrinterVerifier::new(policy.clone())))
+        .with_no_client_auth()
+}
+
+/// Same as [`client_config`], for the rustls version used by `rumqttc`.
+pub(crate) fn rumqttc_client_config(policy: &CertificatePolicy) -> rumqttc_rustls::ClientConfig {
+    let provider = rumqttc_rustls::crypto::ring::default_provider();
+    rumqttc_rustls::ClientConfig::builder_with_provider(Arc::new(provider))
+        .with_safe_default_protocol_versions()
+        .expect("ring supports the default protocol versions")
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(PrinterVerifier::new(policy.clone())))
+        .with_no_client_auth()
+}
+
+/// All signature schemes, accepted without verification by the insecure policy.
+const ALL_SIGNATURE_SCHEMES: [SignatureScheme; 13] = [
+    SignatureScheme::RSA_PKCS1_SHA1,
+    SignatureScheme::ECDSA_SHA1_Legacy,
+    SignatureScheme::RSA_PKCS1_SHA256,
+    SignatureScheme::ECDSA_NISTP256_SHA256,
+    SignatureScheme::RSA_PKCS1_SHA384,
+    SignatureScheme::ECDSA_NISTP384_SHA384,
+    SignatureScheme::RSA_PKCS1_SHA512,
+    SignatureScheme::ECDSA_NISTP521_SHA512,
+    SignatureScheme::RSA_PSS_SHA256,
+    SignatureScheme::RSA_PSS_SHA384,
+    SignatureScheme::RSA_PSS_SHA512,
+    SignatureScheme::ED25519,
+    SignatureScheme::ED448,
+];
+
+/// Verifies the printer certificate for both this crate and `rumqttc`, which depend on different
+/// rustls versions.
 #[derive(Debug)]
-pub(crate) struct NoVerifier;
+pub(crate) struct PrinterVerifier {
+    policy: CertificatePolicy,
+}
+
+impl PrinterVerifier {
+    pub(crate) fn new(policy: CertificatePolicy) -> Self {
+        Self { policy }
+    }
+}
 
-impl ServerCertVerifier for NoVerifier {
+impl ServerCertVerifier for PrinterVerifier {
     fn verify_server_cert(
         &self,
-        _end_entity: &CertificateDer,
-        _intermediates: &[CertificateDer],
+        end_entity: &CertificateDer,
+        intermediates: &[CertificateDer],
         _server_name: &ServerName,
         _ocsp_response: &[u8],
-        _now: UnixTime,
+        now: UnixTime,
     ) -> Result<ServerCertVerified, Error> {
-        Ok(ServerCertVerified::assertion())
+        self.policy
+            .verify_certificate(end_entity, intermediates, now)
+            .map(|()| ServerCertVerified::assertion())
     }
 
     fn verify_tls12_signature(
         &self,
-        _message: &[u8],
-        _cert: &CertificateDer,
-        _dss: &DigitallySignedStruct,
+        message: &[u8],
+        cert: &CertificateDer,
+        dss: &DigitallySignedStruct,
     ) -> Result<HandshakeSignatureValid, Error> {
-        Ok(HandshakeSignatureValid::assertion())
+        if self.policy.is_insecure() {
+            return Ok(HandshakeSignatureValid::assertion());
+        }
+        let algorithms = ring::default_provider().signature_verification_algorithms;
+        verify_tls12_signature(message, cert, dss, &algorithms)
     }
 
     fn verify_tls13_signature(
         &self,
-        _message: &[u8],
-        _cert: &CertificateDer,
-        _dss: &DigitallySignedStruct,
+        message: &[u8],
+        cert: &CertificateDer,
+        dss: &DigitallySignedStruct,
     ) -> Result<HandshakeSignatureValid, Error> {
-        Ok(HandshakeSignatureValid::assertion())
+        if self.policy.is_insecure() {
+            return Ok(HandshakeSignatureValid::assertion());
+        }
+        let algorithms = ring::default_provider().signature_verification_algorithms;
+        verify_tls13_signature(message, cert, dss, &algorithms)
     }
 
     fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
-        vec![
-            SignatureScheme::RSA_PKCS1_SHA1,
-            SignatureScheme::ECDSA_SHA1_Legacy,
-            SignatureScheme::RSA_PKCS1_SHA256,
-            SignatureScheme::ECDSA_NISTP256_SHA256,
-            SignatureScheme::RSA_PKCS1_SHA384,
-            SignatureScheme::ECDSA_NISTP384_SHA384,
-            SignatureScheme::RSA_PKCS1_SHA512,
-            SignatureScheme::ECDSA_NISTP521_SHA512,
-            SignatureScheme::RSA_PSS_SHA256,
-            SignatureScheme::RSA_PSS_SHA384,
-            SignatureScheme::RSA_PSS_SHA512,
-            SignatureScheme::ED25519,
-            SignatureScheme::ED448,
-        ]
+        if self.policy.is_insecure() {
+            return ALL_SIGNATURE_SCHEMES.to_vec();
+        }
+        ring::default_provider()
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+impl rumqttc_rustls::client::danger::ServerCertVerifier for PrinterVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer,
+        intermediates: &[CertificateDer],
+        _server_name: &ServerName,
+        _ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<rumqttc_rustls::client::danger::ServerCertVerified, rumqttc_rustls::Error> {
+        self.policy
+            .verify_certificate(end_entity, intermediates, now)
+            .map(|()| rumqttc_rustls::client::danger::ServerCertVerified::assertion())
+            .map_err(|e| rumqttc_rustls::Error::General(e.to_string()))
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer,
+        dss: &rumqttc_rustls::DigitallySignedStruct,
+    ) -> Result<rumqttc_rustls::client::danger::HandshakeSignatureValid, rumqttc_rustls::Error>
+    {
+        if self.policy.is_insecure() {
+            return Ok(rumqttc_rustls::client::danger::HandshakeSignatureValid::assertion());
+        }
+        let algorithms =
+            rumqttc_rustls::crypto::ring::default_provider().signature_verification_algorithms;
+        rumqttc_rustls::crypto::verify_tls12_signature(message, cert, dss, &algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer,
+        dss: &rumqttc_rustls::DigitallySignedStruct,
+    ) -> Result<rumqttc_rustls::client::danger::HandshakeSignatureValid, rumqttc_rustls::Error>
+    {
+        if self.policy.is_insecure() {
+            return Ok(rumqttc_rustls::client::danger::HandshakeSignatureValid::assertion());
+        }
+        let algorithms =
+            rumqttc_rustls::crypto::ring::default_provider().signature_verification_algorithms;
+        rumqttc_rustls::crypto::verify_tls13_signature(message, cert, dss, &algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rumqttc_rustls::SignatureScheme> {
+        if self.policy.is_insecure() {
+            return ALL_SIGNATURE_SCHEMES
+                .iter()
+                .map(|&scheme| rumqttc_rustls::SignatureScheme::from(u16::from(scheme)))
+                .collect();
+        }
+        rumqttc_rustls::crypto::ring::default_provider()
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+/// Extract the subject common name from a DER encoded X.509 certificate.
+fn subject_common_name(certificate: &CertificateDer) -> Option<SmolStr> {
+    const SET: u8 = 0x31;
+    const SEQUENCE: u8 = 0x30;
+    const OBJECT_IDENTIFIER: u8 = 0x06;
+    const COMMON_NAME: [u8; 3] = [0x55, 0x04, 0x03];
+
+    let certificate = EndEntityCert::try_from(certificate).ok()?;
+    // The subject is a sequence of relative distinguished names, each a set of attributes.
+    let mut subject = certificate.subject();
+    while !subject.is_empty() {
+        let (_, name, rest) = der_element(subject, SET)?;
+        subject = rest;
+        let (_, attribute, _) = der_element(name, SEQUENCE)?;
+        let (_, oid, value) = der_element(attribute, OBJECT_IDENTIFIER)?;
+        if oid == COMMON_NAME {
+            let (_, value, _) = der_any(value)?;
+            return std::str::from_utf8(value).ok().map(SmolStr::new);
+        }
+    }
+    None
+}
+
+/// Split a DER element with the given tag into `(tag, content, rest)`.
+fn der_element(input: &[u8], tag: u8) -> Option<(u8, &[u8], &[u8])> {
+    der_any(input).filter(|(actual, _, _)| *actual == tag)
+}
+
+/// Split any DER element into `(tag, content, rest)`.
+fn der_any(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, input) = input.split_first()?;
+    let (&length, mut input) = input.split_first()?;
+    let length = if length < 0x80 {
+        usize::from(length)
+    } else {
+        let count = usize::from(length & 0x7f);
+        if count == 0 || count > 4 || input.len() < count {
+            return None;
+        }
+        let (bytes, rest) = input.split_at(count);
+        input = rest;
+        bytes
+            .iter()
+            .fold(0usize, |length, byte| (length << 8) | usize::from(*byte))
+    };
+    if input.len() < length {
+        return None;
+    }
+    let (content, rest) = input.split_at(length);
+    Some((tag, content, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio_rustls::rustls::pki_types::{CertificateDer, UnixTime};
+
+    use super::{subject_common_name, CertificatePolicy};
+
+    const CA: &[u8] = include_bytes!("testdata/printer_ca.der");
+    const CA_PEM: &[u8] = include_bytes!("testdata/printer_ca.pem");
+    const PRINTER: &[u8] = include_bytes!("testdata/printer_01S00C000000000.der");
+    const OTHER_PRINTER: &[u8] = include_bytes!("testdata/printer_01P00A999999999.der");
+
+    fn now() -> UnixTime {
+        // 2030-01-01, while the test certificates are valid.
+        UnixTime::since_unix_epoch(Duration::from_secs(1_893_456_000))
+    }
+
+    #[test]
+    fn extracts_common_name() {
+        let common_name = |der: &[u8]| subject_common_name(&CertificateDer::from(der));
+        assert_eq!(common_name(PRINTER).as_deref(), Some("01S00C000000000"));
+        assert_eq!(common_name(CA).as_deref(), Some("Test Printer CA"));
+        assert_eq!(common_name(&PRINTER[..100]), None);
+    }
+
+    #[test]
+    fn trust_on_first_use() {
+        let policy = CertificatePolicy::trust_on_first_use();
+        let CertificatePolicy::Pinned(pin) = &policy else {
+            unreachable!();
+        };
+        assert_eq!(pin.fingerprint(), None);
+
+        let printer = CertificateDer::from(PRINTER);
+        policy.verify_certificate(&printer, &[], now()).unwrap();
+        let fingerprint = pin.fingerprint().unwrap();
+
+        // Clones share the pin.
+        let shared = policy.clone();
+        shared.verify_certificate(&printer, &[], now()).unwrap();
+        assert!(shared
+            .verify_certificate(&CertificateDer::from(OTHER_PRINTER), &[], now())
+            .is_err());
+
+        // The pin can be restored.
+        let restored = CertificatePolicy::pinned(fingerprint);
+        restored.verify_certificate(&printer, &[], now()).unwrap();
+    }
+
+    #[test]
+    fn ca_requires_serial_as_common_name() {
+        let policy =
+            CertificatePolicy::ca([CertificateDer::from(CA.to_vec())], "01S00C000000000").unwrap();
+        policy
+            .verify_certificate(&CertificateDer::from(PRINTER), &[], now())
+            .unwrap();
+        assert!(policy
+            .verify_certificate(&CertificateDer::from(OTHER_PRINTER), &[], now())
+            .is_err());
+
+        let policy = CertificatePolicy::ca_pem(CA_PEM, "01S00C000000000").unwrap();
+        policy
+            .verify_certificate(&CertificateDer::from(PRINTER), &[], now())
+            .unwrap();
+        assert!(CertificatePolicy::ca_pem(b"", "01S00C000000000").is_err());
+
+        // Self-issued certificates are rejected.
+        let policy = CertificatePolicy::ca(
+            [CertificateDer::from(OTHER_PRINTER.to_vec())],
+            "01S00C000000000",
+        )
+        .unwrap();
+        assert!(policy
+            .verify_certificate(&CertificateDer::from(PRINTER), &[], now())
+            .is_err());
     }
 }